@@ -0,0 +1,423 @@
+//! The binary format of a serialized [`ScopeIndex`](crate::ScopeIndex).
+//!
+//! All integers are stored as little-endian `u32`, and no alignment is
+//! required, so the data can be read directly out of an arbitrary byte buffer.
+//! The format is laid out as follows:
+//!
+//! ```text
+//! header:
+//!   magic:       b"JSSI"
+//!   version:     u32
+//!   num_ranges:  u32
+//!   num_scopes:  u32
+//!   num_names:   u32
+//!   strings_len: u32
+//! ranges:  num_ranges * (offset: u32, scope_idx: u32)
+//! scopes:  num_scopes * (start: u32, end: u32, name_idx: u32, minified_name_idx: u32,
+//!                        parent_idx: u32)
+//! names:   num_names * (string_offset: u32, string_len: u32)
+//! strings: strings_len bytes of UTF-8
+//! ```
+//!
+//! The `ranges` are sorted by their `offset`, and their `scope_idx` is either
+//! an index into `scopes` or the global scope sentinel. The `scopes` are sorted
+//! by their `start`, and refer to their name and minified name in `names` (or
+//! the anonymous scope sentinel), and to their parent, which always comes before its
+//! children (or the global scope sentinel). The `names` in turn point into the
+//! `strings` section, which has to be valid UTF-8 as a whole, and each name has
+//! to start and end on a char boundary.
+
+use std::fmt;
+use std::io::{self, Write};
+
+use indexmap::IndexSet;
+
+use crate::scope_index::{ScopeEntry, ANONYMOUS_SCOPE_SENTINEL, GLOBAL_SCOPE_SENTINEL};
+
+/// The magic bytes at the start of a serialized [`ScopeIndex`](crate::ScopeIndex).
+const MAGIC: [u8; 4] = *b"JSSI";
+
+/// The current version of the binary format.
+const VERSION: u32 = 1;
+
+/// Size of the header, in bytes.
+const HEADER_SIZE: usize = 24;
+
+/// Size of a single entry in the `ranges` and `names` tables, in bytes.
+const ENTRY_SIZE: usize = 8;
+
+/// Size of a single entry in the `scopes` table, in bytes.
+const SCOPE_SIZE: usize = 20;
+
+/// Writes the given `names`, `scopes` and `ranges` in the binary format.
+pub(crate) fn write<W: Write>(
+    mut writer: W,
+    names: &IndexSet<String>,
+    scopes: &[ScopeEntry],
+    ranges: &[(u32, u32)],
+) -> io::Result<()> {
+    let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "scope index is too large");
+    let num_ranges: u32 = ranges.len().try_into().map_err(|_| too_large())?;
+    let num_scopes: u32 = scopes.len().try_into().map_err(|_| too_large())?;
+    let num_names: u32 = names.len().try_into().map_err(|_| too_large())?;
+    let strings_len = names.iter().map(|name| name.len()).sum::<usize>();
+    let strings_len: u32 = strings_len.try_into().map_err(|_| too_large())?;
+
+    writer.write_all(&MAGIC)?;
+    for value in [VERSION, num_ranges, num_scopes, num_names, strings_len] {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    for &(offset, scope_idx) in ranges {
+        writer.write_all(&offset.to_le_bytes())?;
+        writer.write_all(&scope_idx.to_le_bytes())?;
+    }
+
+    for scope in scopes {
+        for value in [
+            scope.start,
+            scope.end,
+            scope.name_idx,
+            scope.minified_name_idx,
+            scope.parent_idx,
+        ] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+    }
+
+    let mut string_offset = 0u32;
+    for name in names {
+        // this can not overflow, as the sum of all lengths fits into `strings_len`
+        let len = name.len() as u32;
+        writer.write_all(&string_offset.to_le_bytes())?;
+        writer.write_all(&len.to_le_bytes())?;
+        string_offset += len;
+    }
+
+    for name in names {
+        writer.write_all(name.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// A validated, borrowed view of the binary format.
+#[derive(Clone, Copy)]
+pub(crate) struct Format<'data> {
+    ranges: &'data [u8],
+    scopes: &'data [u8],
+    names: &'data [u8],
+    strings: &'data str,
+}
+
+impl<'data> Format<'data> {
+    /// Parses and validates the binary format.
+    ///
+    /// This makes sure that all the accessors of the returned [`Format`] are
+    /// infallible.
+    pub(crate) fn parse(data: &'data [u8]) -> Result<Self, ScopeIndexFormatError> {
+        use ScopeIndexFormatErrorKind::*;
+
+        let header = data.get(..HEADER_SIZE).ok_or(UnexpectedEof)?;
+        if header[..4] != MAGIC {
+            return Err(BadMagic.into());
+        }
+        let version = read_u32(header, 4);
+        if version != VERSION {
+            return Err(UnsupportedVersion(version).into());
+        }
+        let num_ranges = read_u32(header, 8) as usize;
+        let num_scopes = read_u32(header, 12) as usize;
+        let num_names = read_u32(header, 16) as usize;
+        let strings_len = read_u32(header, 20) as usize;
+
+        let rest = &data[HEADER_SIZE..];
+        let (ranges, rest) = split_at(rest, num_ranges.checked_mul(ENTRY_SIZE))?;
+        let (scopes, rest) = split_at(rest, num_scopes.checked_mul(SCOPE_SIZE))?;
+        let (names, rest) = split_at(rest, num_names.checked_mul(ENTRY_SIZE))?;
+        let (strings, _rest) = split_at(rest, Some(strings_len))?;
+        let strings = std::str::from_utf8(strings).map_err(|_| InvalidName)?;
+
+        let format = Self {
+            ranges,
+            scopes,
+            names,
+            strings,
+        };
+
+        for idx in 0..num_names {
+            let (start, len) = format.name_entry(idx);
+            strings
+                .get(start as usize..)
+                .and_then(|s| s.get(..len as usize))
+                .ok_or(InvalidName)?;
+        }
+
+        let mut prev_start = 0;
+        for idx in 0..num_scopes {
+            let scope = format.scope(idx as u32);
+            let is_valid_name =
+                |name_idx| name_idx == ANONYMOUS_SCOPE_SENTINEL || (name_idx as usize) < num_names;
+            let valid_name =
+                is_valid_name(scope.name_idx) && is_valid_name(scope.minified_name_idx);
+            // parents have to come first, which also rules out cycles
+            let valid_parent =
+                scope.parent_idx == GLOBAL_SCOPE_SENTINEL || (scope.parent_idx as usize) < idx;
+            if scope.start < prev_start || scope.start > scope.end || !valid_name || !valid_parent {
+                return Err(InvalidScope.into());
+            }
+            prev_start = scope.start;
+        }
+
+        let mut prev_offset = 0;
+        for idx in 0..num_ranges {
+            let (offset, scope_idx) = format.range(idx);
+            let valid_scope =
+                scope_idx == GLOBAL_SCOPE_SENTINEL || (scope_idx as usize) < num_scopes;
+            if offset < prev_offset || !valid_scope {
+                return Err(InvalidRange.into());
+            }
+            prev_offset = offset;
+        }
+
+        Ok(format)
+    }
+
+    /// The number of entries in the `ranges` table.
+    pub(crate) fn num_ranges(&self) -> usize {
+        self.ranges.len() / ENTRY_SIZE
+    }
+
+    /// The number of entries in the `scopes` table.
+    pub(crate) fn num_scopes(&self) -> usize {
+        self.scopes.len() / SCOPE_SIZE
+    }
+
+    /// The number of entries in the `names` table.
+    pub(crate) fn num_names(&self) -> usize {
+        self.names.len() / ENTRY_SIZE
+    }
+
+    /// Returns the entry of the `scopes` table at `idx`.
+    pub(crate) fn scope(&self, idx: u32) -> ScopeEntry {
+        let offset = idx as usize * SCOPE_SIZE;
+        ScopeEntry {
+            start: read_u32(self.scopes, offset),
+            end: read_u32(self.scopes, offset + 4),
+            name_idx: read_u32(self.scopes, offset + 8),
+            minified_name_idx: read_u32(self.scopes, offset + 12),
+            parent_idx: read_u32(self.scopes, offset + 16),
+        }
+    }
+
+    /// Returns the `(offset, scope_idx)` entry of the `ranges` table at `idx`.
+    pub(crate) fn range(&self, idx: usize) -> (u32, u32) {
+        let offset = idx * ENTRY_SIZE;
+        (
+            read_u32(self.ranges, offset),
+            read_u32(self.ranges, offset + 4),
+        )
+    }
+
+    /// Returns the name at `idx` of the `names` table.
+    pub(crate) fn name(&self, idx: usize) -> &'data str {
+        let (start, len) = self.name_entry(idx);
+        // the name was validated to lie on char boundaries in `parse`
+        &self.strings[start as usize..][..len as usize]
+    }
+
+    fn name_entry(&self, idx: usize) -> (u32, u32) {
+        let offset = idx * ENTRY_SIZE;
+        (
+            read_u32(self.names, offset),
+            read_u32(self.names, offset + 4),
+        )
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&data[offset..offset + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+fn split_at(data: &[u8], mid: Option<usize>) -> Result<(&[u8], &[u8]), ScopeIndexFormatError> {
+    match mid {
+        Some(mid) if mid <= data.len() => Ok(data.split_at(mid)),
+        _ => Err(ScopeIndexFormatErrorKind::UnexpectedEof.into()),
+    }
+}
+
+/// The kind of a [`ScopeIndexFormatError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ScopeIndexFormatErrorKind {
+    /// The data does not start with the expected magic bytes.
+    BadMagic,
+    /// The data was written with an unsupported version of the format.
+    UnsupportedVersion(u32),
+    /// The data ended unexpectedly.
+    UnexpectedEof,
+    /// The string table is not valid UTF-8, or a name points outside of it
+    /// or into the middle of a char.
+    InvalidName,
+    /// The scopes are not sorted, or refer to a non-existing name or parent.
+    InvalidScope,
+    /// The ranges are not sorted, or refer to a non-existing scope.
+    InvalidRange,
+}
+
+/// An Error that can happen when loading a serialized [`ScopeIndex`](crate::ScopeIndex).
+#[derive(Debug)]
+pub struct ScopeIndexFormatError {
+    kind: ScopeIndexFormatErrorKind,
+}
+
+impl ScopeIndexFormatError {
+    /// The kind of this error.
+    pub fn kind(&self) -> ScopeIndexFormatErrorKind {
+        self.kind
+    }
+}
+
+impl From<ScopeIndexFormatErrorKind> for ScopeIndexFormatError {
+    fn from(kind: ScopeIndexFormatErrorKind) -> Self {
+        Self { kind }
+    }
+}
+
+impl std::error::Error for ScopeIndexFormatError {}
+
+impl fmt::Display for ScopeIndexFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ScopeIndexFormatErrorKind::BadMagic => f.write_str("invalid scope index magic"),
+            ScopeIndexFormatErrorKind::UnsupportedVersion(version) => {
+                write!(f, "unsupported scope index version {version}")
+            }
+            ScopeIndexFormatErrorKind::UnexpectedEof => {
+                f.write_str("unexpected end of scope index data")
+            }
+            ScopeIndexFormatErrorKind::InvalidName => f.write_str("invalid scope index name"),
+            ScopeIndexFormatErrorKind::InvalidScope => f.write_str("invalid scope index scope"),
+            ScopeIndexFormatErrorKind::InvalidRange => f.write_str("invalid scope index range"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format_bytes() -> Vec<u8> {
+        let names: IndexSet<_> = ["parent", "child"].into_iter().map(String::from).collect();
+        let scopes = [
+            ScopeEntry {
+                start: 5,
+                end: 20,
+                name_idx: 0,
+                minified_name_idx: ANONYMOUS_SCOPE_SENTINEL,
+                parent_idx: GLOBAL_SCOPE_SENTINEL,
+            },
+            ScopeEntry {
+                start: 10,
+                end: 15,
+                name_idx: 1,
+                minified_name_idx: 0,
+                parent_idx: 0,
+            },
+        ];
+        let ranges = [
+            (0, GLOBAL_SCOPE_SENTINEL),
+            (5, 0),
+            (10, 1),
+            (15, 0),
+            (20, GLOBAL_SCOPE_SENTINEL),
+        ];
+        let mut buf = vec![];
+        write(&mut buf, &names, &scopes, &ranges).unwrap();
+        buf
+    }
+
+    #[test]
+    fn roundtrip() {
+        let buf = format_bytes();
+        assert_eq!(
+            buf.len(),
+            HEADER_SIZE + 5 * ENTRY_SIZE + 2 * SCOPE_SIZE + 2 * ENTRY_SIZE + 11
+        );
+
+        let format = Format::parse(&buf).unwrap();
+        assert_eq!(format.num_ranges(), 5);
+        assert_eq!(format.num_scopes(), 2);
+        assert_eq!(format.num_names(), 2);
+        assert_eq!(format.range(2), (10, 1));
+        assert_eq!(format.scope(1).parent_idx, 0);
+        assert_eq!(format.name(0), "parent");
+        assert_eq!(format.name(1), "child");
+    }
+
+    #[test]
+    fn invalid_data() {
+        let kind = |buf: &[u8]| Format::parse(buf).err().map(|err| err.kind());
+
+        let buf = format_bytes();
+        assert_eq!(
+            kind(&buf[..10]),
+            Some(ScopeIndexFormatErrorKind::UnexpectedEof)
+        );
+        assert_eq!(
+            kind(&buf[..buf.len() - 1]),
+            Some(ScopeIndexFormatErrorKind::UnexpectedEof)
+        );
+
+        let mut bad_magic = buf.clone();
+        bad_magic[0] = b'X';
+        assert_eq!(kind(&bad_magic), Some(ScopeIndexFormatErrorKind::BadMagic));
+
+        let mut bad_version = buf.clone();
+        bad_version[4] = 42;
+        assert_eq!(
+            kind(&bad_version),
+            Some(ScopeIndexFormatErrorKind::UnsupportedVersion(42))
+        );
+
+        // the `scope_idx` of the third range
+        let mut bad_range = buf.clone();
+        bad_range[HEADER_SIZE + 2 * ENTRY_SIZE + 4] = 2;
+        assert_eq!(
+            kind(&bad_range),
+            Some(ScopeIndexFormatErrorKind::InvalidRange)
+        );
+
+        // the `parent_idx` of the first scope
+        let mut bad_scope = buf.clone();
+        bad_scope[HEADER_SIZE + 5 * ENTRY_SIZE + 16] = 1;
+        assert_eq!(
+            kind(&bad_scope),
+            Some(ScopeIndexFormatErrorKind::InvalidScope)
+        );
+
+        // the first byte of the `child` string
+        let mut bad_name = buf;
+        let len = bad_name.len();
+        bad_name[len - 5] = 0xff;
+        assert_eq!(
+            kind(&bad_name),
+            Some(ScopeIndexFormatErrorKind::InvalidName)
+        );
+    }
+
+    #[test]
+    fn name_inside_char() {
+        let names: IndexSet<_> = ["ö"].into_iter().map(String::from).collect();
+        let mut buf = vec![];
+        write(&mut buf, &names, &[], &[]).unwrap();
+        assert_eq!(Format::parse(&buf).unwrap().name(0), "ö");
+
+        // the `string_len` of the name, ending in the middle of the `ö`
+        buf[HEADER_SIZE + 4] = 1;
+        let err = Format::parse(&buf).err().unwrap();
+        assert_eq!(err.kind(), ScopeIndexFormatErrorKind::InvalidName);
+    }
+}
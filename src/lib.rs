@@ -4,19 +4,44 @@
 use std::fmt::Display;
 use std::ops::Range;
 
+mod format;
 mod name_resolver;
 mod scope_index;
+mod scope_index_view;
 mod scope_name;
 mod source;
+mod source_scope_index;
 mod swc;
 
-pub use name_resolver::NameResolver;
-pub use scope_index::{ScopeIndex, ScopeIndexError, ScopeLookupResult};
+pub use format::{ScopeIndexFormatError, ScopeIndexFormatErrorKind};
+pub use name_resolver::{
+    NameResolution, NameResolver, OriginalLocation, ResolvedComponent, ResolvedName, ResolvedScope,
+};
+pub use scope_index::{
+    IndexedScope, OwnedScopeLookupResult, ScopeIndex, ScopeIndexError, ScopeIndexErrorKind,
+    ScopeIndexStats, ScopeLookupResult, ScopeRepair,
+};
+pub use scope_index_view::ScopeIndexView;
 pub use scope_name::{NameComponent, ScopeName};
-pub use source::{SourceContext, SourceContextError, SourcePosition};
+pub use source::{
+    ColumnUnit, ContextLines, FramePosition, LineTerminators, SourceContext, SourceContextError,
+    SourceContextOptions, SourceEncoding, SourcePosition,
+};
+pub use source_scope_index::SourceScopeIndex;
 use swc_common::Spanned;
 
 /// The Scopes extracted from a piece of JS Code.
+///
+/// With the `serde` feature enabled, this serializes as a list of
+/// `[range, name]` pairs, where `range` is a `{"start": .., "end": ..}` object
+/// and `name` is either `null` or a serialized [`ScopeName`]:
+///
+/// ```json
+/// [
+///   [{"start": 20, "end": 28}, [{"text": "arrowFnExpr", "range": {"start": 6, "end": 17}}]],
+///   [{"start": 30, "end": 45}, null]
+/// ]
+/// ```
 pub type Scopes = Vec<(Range<u32>, Option<ScopeName>)>;
 
 /// Extracts function scopes from the given JS-like `src`.
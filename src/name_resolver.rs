@@ -1,18 +1,53 @@
-use sourcemap::DecodedMap;
+use std::cmp::Reverse;
+use std::ops::Range;
+use std::sync::OnceLock;
 
-use crate::{NameComponent, ScopeName, SourceContext};
+use sourcemap::{DecodedMap, SourceMap, Token};
+
+use crate::{
+    extract_scope_names, ColumnUnit, NameComponent, ScopeIndex, ScopeName, Scopes, SourceContext,
+    SourcePosition,
+};
 
 /// A structure for resolving [`ScopeName`]s in minified code to their original names
 /// using information contained in a [`DecodedMap`].
 pub struct NameResolver<'a, T> {
     ctx: &'a SourceContext<T>,
     sourcemap: &'a DecodedMap,
+    original_sources: Option<OriginalSources<'a>>,
 }
 
-impl<'a, T: AsRef<str>> NameResolver<'a, T> {
+impl<'a, T> NameResolver<'a, T> {
     /// Construct a new [`NameResolver`] from a [`SourceContext`] (for the minified source) and a [`DecodedMap`].
     pub fn new(ctx: &'a SourceContext<T>, sourcemap: &'a DecodedMap) -> Self {
-        Self { ctx, sourcemap }
+        Self {
+            ctx,
+            sourcemap,
+            original_sources: None,
+        }
+    }
+
+    /// Enables resolving scope names by parsing the original sources embedded
+    /// in the source map `sourcesContent`.
+    ///
+    /// When [`NameResolver::resolve_scope`] can not fully resolve a scope name
+    /// from the source map alone, it maps the start of the scope to its original
+    /// position and uses the name of the innermost scope enclosing that position
+    /// in the original source, as extracted by [`extract_scope_names`].
+    ///
+    /// Each original source is parsed at most once, the first time it is needed.
+    pub fn with_original_sources(mut self) -> Self {
+        let mut maps = vec![];
+        collect_source_maps(self.sourcemap, &mut maps);
+        let maps = maps
+            .into_iter()
+            .map(|sm| {
+                let sources = (0..sm.get_source_count()).map(|_| OnceLock::new());
+                (sm, sources.collect())
+            })
+            .collect();
+        self.original_sources = Some(OriginalSources { maps });
+        self
     }
 
     /// Resolves the given minified [`ScopeName`] to the original name.
@@ -20,15 +55,204 @@ impl<'a, T: AsRef<str>> NameResolver<'a, T> {
     /// This tries to resolve each [`NameComponent`] by looking up its source
     /// range in the [`DecodedMap`], using the token's `name` (as defined in the
     /// sourcemap `names`) when possible.
+    ///
+    /// See [`NameResolver::resolve_name_detailed`] for details about how each
+    /// component was resolved.
     pub fn resolve_name(&self, name: &ScopeName) -> String {
-        name.components()
-            .map(|c| self.try_map_token(c).unwrap_or_else(|| c.text()))
-            .collect::<String>()
+        self.resolve_name_detailed(name).to_string()
+    }
+
+    /// Resolves the given minified [`ScopeName`] to the original name, reporting
+    /// how each of its components was resolved.
+    ///
+    /// Components that could not be resolved keep their minified text.
+    pub fn resolve_name_detailed<'n>(&'n self, name: &'n ScopeName) -> ResolvedName<'n> {
+        let components = name
+            .components()
+            .map(|c| self.resolve_component(c))
+            .collect();
+        ResolvedName { components }
+    }
+
+    /// Resolves the scope with the given `range` and minified `name`, as
+    /// returned from [`extract_scope_names`](crate::extract_scope_names).
+    ///
+    /// In addition to the resolved name, this returns the original locations
+    /// of the scope and its name, which allows linking to the original source.
+    ///
+    /// See [`NameResolver::with_original_sources`] for resolving names that are
+    /// missing from the source map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::{extract_scope_names, NameResolver, SourceContext};
+    ///
+    /// let minified = "function t(){}export default t;";
+    /// let map = r#"{
+    ///     "version": 3,
+    ///     "names": ["abcd"],
+    ///     "sources": ["original.js"],
+    ///     "mappings": "AACA,SAASA,oBACMA"
+    /// }"#;
+    /// let sm = sourcemap::decode_slice(map.as_bytes()).unwrap();
+    /// let ctx = SourceContext::new(minified).unwrap();
+    /// let resolver = NameResolver::new(&ctx, &sm);
+    ///
+    /// let (range, name) = extract_scope_names(minified).unwrap().remove(0);
+    /// let scope = resolver.resolve_scope(range, name.as_ref());
+    ///
+    /// assert_eq!(scope.name.unwrap().to_string(), "abcd");
+    ///
+    /// let name_location = scope.name_location.unwrap();
+    /// assert_eq!(name_location.source, Some("original.js"));
+    /// assert_eq!((name_location.position.line, name_location.position.column), (1, 9));
+    ///
+    /// let scope_location = scope.scope_location.unwrap();
+    /// assert_eq!((scope_location.position.line, scope_location.position.column), (1, 0));
+    /// ```
+    pub fn resolve_scope<'n>(
+        &'n self,
+        range: Range<u32>,
+        name: Option<&'n ScopeName>,
+    ) -> ResolvedScope<'n> {
+        let mut name = name.map(|name| self.resolve_name_detailed(name));
+        let scope_token = self.find_token(range.start);
+
+        if !name.as_ref().is_some_and(ResolvedName::is_fully_resolved) {
+            if let Some(original) = scope_token.and_then(|t| self.resolve_original_scope(&t)) {
+                name = Some(original);
+            }
+        }
+
+        // the last identifier is the name of the function itself, as in `Foo.bar`
+        let name_location = name
+            .iter()
+            .flat_map(|name| name.components.iter().rev())
+            .find_map(|c| c.original);
+        let scope_location = scope_token.map(|token| OriginalLocation::from_token(&token));
+
+        ResolvedScope {
+            range,
+            name,
+            name_location,
+            scope_location,
+        }
+    }
+
+    fn resolve_component<'n>(&'n self, c: &'n NameComponent) -> ResolvedComponent<'n> {
+        let unresolved = |resolution, original| ResolvedComponent {
+            text: c.text(),
+            resolution,
+            original,
+        };
+
+        let Some(range) = c.range() else {
+            return unresolved(NameResolution::Interpolation, None);
+        };
+        let Some(token) = self.find_token(range.start) else {
+            return unresolved(NameResolution::Unresolved, None);
+        };
+
+        let original = Some(OriginalLocation::from_token(&token));
+        if let Some(name) = token.get_name() {
+            return ResolvedComponent {
+                text: name,
+                resolution: NameResolution::SourceMapName,
+                original,
+            };
+        }
+
+        // If the token at the identifier position has no name, check the
+        // immediately preceding token. Some source map generators (e.g.
+        // TypeScript) attach the original function name to the `function`
+        // keyword token rather than the identifier that follows it.
+        // We only use the preceding token's name if it maps to the same
+        // original source position, indicating it's part of the same mapping.
+        if token.get_dst_col() > 0 {
+            if let Some(prev_token) = self
+                .sourcemap
+                .lookup_token(token.get_dst_line(), token.get_dst_col() - 1)
+            {
+                if prev_token.get_src_id() == token.get_src_id()
+                    && prev_token.get_src_line() == token.get_src_line()
+                    && prev_token.get_src_col() == token.get_src_col()
+                {
+                    if let Some(name) = prev_token.get_name() {
+                        return ResolvedComponent {
+                            text: name,
+                            resolution: NameResolution::PrecedingToken,
+                            original,
+                        };
+                    }
+                }
+            }
+        }
+
+        unresolved(NameResolution::Unresolved, original)
     }
 
-    fn try_map_token(&self, c: &NameComponent) -> Option<&str> {
-        let range = c.range()?;
-        let source_position = self.ctx.offset_to_position(range.start)?;
+    /// Resolves the name of the innermost scope enclosing the original position
+    /// of `token`, by parsing the original source.
+    fn resolve_original_scope(&self, token: &Token<'a>) -> Option<ResolvedName<'_>> {
+        let original = self.original_source(token)?;
+        let position = SourcePosition::new(token.get_src_line(), token.get_src_col());
+        let offset = original.ctx.position_to_offset(position)?;
+        let scope = original.index.lookup_scope(offset)?;
+        let (_, name) = original.scopes.get(scope.id as usize)?;
+
+        let components = name
+            .as_ref()?
+            .components()
+            .map(|c| {
+                let original = c.range().and_then(|range| {
+                    let position = original.ctx.offset_to_position(range.start)?;
+                    Some(OriginalLocation {
+                        source: token.get_source(),
+                        position,
+                    })
+                });
+                ResolvedComponent {
+                    text: c.text(),
+                    resolution: NameResolution::OriginalSource,
+                    original,
+                }
+            })
+            .collect();
+        Some(ResolvedName { components })
+    }
+
+    /// Returns the parsed original source of `token`, parsing it on first use.
+    fn original_source(&self, token: &Token<'a>) -> Option<&OriginalSource<'a>> {
+        let sm = token.sourcemap();
+        let (_, sources) = self
+            .original_sources
+            .as_ref()?
+            .maps
+            .iter()
+            .find(|(map, _)| std::ptr::eq(*map, sm))?;
+        let src_id = token.get_src_id();
+
+        sources
+            .get(src_id as usize)?
+            .get_or_init(|| {
+                let contents = sm.get_source_contents(src_id)?;
+                let mut scopes = extract_scope_names(contents).ok()?;
+                // sort the scopes the same way as the index, so the scope ids
+                // of the index correspond to positions in `scopes`
+                scopes.sort_by_key(|(range, _)| (range.start, Reverse(range.end)));
+                let ranges = scopes.iter().map(|(range, _)| (range.clone(), None));
+                let index = ScopeIndex::new(ranges.collect()).ok()?;
+                let ctx = SourceContext::new(contents).ok()?;
+                Some(OriginalSource { ctx, scopes, index })
+            })
+            .as_ref()
+    }
+
+    /// Finds the sourcemap token matching the given `offset` of the minified source.
+    fn find_token(&self, offset: u32) -> Option<Token<'a>> {
+        // source maps always use UTF-16 columns
+        let source_position = self.ctx.offset_to_position_in(offset, ColumnUnit::Utf16)?;
         let token = self
             .sourcemap
             .lookup_token(source_position.line, source_position.column)?;
@@ -36,32 +260,141 @@ impl<'a, T: AsRef<str>> NameResolver<'a, T> {
         let is_exactish_match = token.get_dst_line() == source_position.line
             && token.get_dst_col() >= source_position.column.saturating_sub(1);
 
-        if is_exactish_match {
-            if let Some(name) = token.get_name() {
-                return Some(name);
-            }
+        is_exactish_match.then_some(token)
+    }
+}
 
-            // If the token at the identifier position has no name, check the
-            // immediately preceding token. Some source map generators (e.g.
-            // TypeScript) attach the original function name to the `function`
-            // keyword token rather than the identifier that follows it.
-            // We only use the preceding token's name if it maps to the same
-            // original source position, indicating it's part of the same mapping.
-            if token.get_dst_col() > 0 {
-                if let Some(prev_token) = self
-                    .sourcemap
-                    .lookup_token(token.get_dst_line(), token.get_dst_col() - 1)
-                {
-                    if prev_token.get_src_id() == token.get_src_id()
-                        && prev_token.get_src_line() == token.get_src_line()
-                        && prev_token.get_src_col() == token.get_src_col()
-                    {
-                        return prev_token.get_name();
-                    }
+/// The original sources of all the [`SourceMap`]s making up a [`DecodedMap`],
+/// indexed by source id.
+struct OriginalSources<'a> {
+    maps: Vec<(&'a SourceMap, Vec<OnceLock<Option<OriginalSource<'a>>>>)>,
+}
+
+/// An original source parsed from the source map `sourcesContent`.
+struct OriginalSource<'a> {
+    ctx: SourceContext<&'a str>,
+    /// The scopes of the source, sorted like in `index`.
+    scopes: Scopes,
+    /// An index over the ranges of `scopes`, to find the innermost scope.
+    index: ScopeIndex,
+}
+
+fn collect_source_maps<'a>(map: &'a DecodedMap, maps: &mut Vec<&'a SourceMap>) {
+    match map {
+        DecodedMap::Regular(sm) => maps.push(sm),
+        DecodedMap::Hermes(smh) => maps.push(smh),
+        DecodedMap::Index(smi) => {
+            for section in smi.sections() {
+                if let Some(map) = section.get_sourcemap() {
+                    collect_source_maps(map, maps);
                 }
             }
         }
+    }
+}
+
+/// A scope resolved by [`NameResolver::resolve_scope`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ResolvedScope<'a> {
+    /// The byte range of the scope in the minified source.
+    pub range: Range<u32>,
+    /// The resolved name of the scope, or `None` for anonymous scopes.
+    pub name: Option<ResolvedName<'a>>,
+    /// The original location of the name of the scope.
+    ///
+    /// For names consisting of multiple components, like `Foo.bar`, this is
+    /// the location of the last component that the source map has a token for.
+    pub name_location: Option<OriginalLocation<'a>>,
+    /// The original location of the start of the scope, if the source map has
+    /// a token for it.
+    pub scope_location: Option<OriginalLocation<'a>>,
+}
+
+/// A [`ScopeName`] resolved by [`NameResolver::resolve_name_detailed`].
+///
+/// Displaying the name results in the same string as [`NameResolver::resolve_name`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ResolvedName<'a> {
+    /// The resolved components of the name.
+    pub components: Vec<ResolvedComponent<'a>>,
+}
+
+impl ResolvedName<'_> {
+    /// Whether all the components of the name that refer to the minified
+    /// source were resolved to their original names.
+    pub fn is_fully_resolved(&self) -> bool {
+        self.components
+            .iter()
+            .all(|c| c.resolution != NameResolution::Unresolved)
+    }
+}
+
+impl std::fmt::Display for ResolvedName<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for c in &self.components {
+            f.write_str(c.text)?;
+        }
+        Ok(())
+    }
+}
+
+/// A [`NameComponent`] resolved by [`NameResolver::resolve_name_detailed`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ResolvedComponent<'a> {
+    /// The resolved text of the component, or the minified text if it could
+    /// not be resolved.
+    pub text: &'a str,
+    /// How the component was resolved.
+    pub resolution: NameResolution,
+    /// The original location of the component, if the source map has a token
+    /// for it.
+    pub original: Option<OriginalLocation<'a>>,
+}
 
-        None
+/// How a [`ResolvedComponent`] was resolved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum NameResolution {
+    /// The component is synthetic text that does not need to be resolved,
+    /// like the `.` in `Foo.bar`.
+    Interpolation,
+    /// The name of the source map token at the component was used.
+    SourceMapName,
+    /// The name of the source map token preceding the component was used, as
+    /// it maps to the same original position.
+    PrecedingToken,
+    /// The name of the scope enclosing the original position was used, as
+    /// extracted from the original source in the source map `sourcesContent`.
+    ///
+    /// See [`NameResolver::with_original_sources`].
+    OriginalSource,
+    /// The component could not be resolved and keeps its minified text.
+    Unresolved,
+}
+
+/// A location inside of an original source, as referenced by a source map.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OriginalLocation<'a> {
+    /// The name of the original source, as given in the source map `sources`.
+    pub source: Option<&'a str>,
+    /// The 0-based position inside of the original source.
+    pub position: SourcePosition,
+}
+
+impl<'a> OriginalLocation<'a> {
+    fn from_token(token: &Token<'a>) -> Self {
+        Self {
+            source: token.get_source(),
+            position: SourcePosition::new(token.get_src_line(), token.get_src_col()),
+        }
     }
 }
@@ -1,12 +1,19 @@
+use std::cmp::Reverse;
+use std::io;
 use std::ops::Range;
+use std::sync::OnceLock;
 
 use indexmap::IndexSet;
 
+use crate::format::{self, ScopeIndexFormatError};
+use crate::{NameResolver, ScopeIndexView, Scopes};
+
 /// An indexed structure of scopes that allows quick lookup by byte offset.
 ///
 /// Construction of the index will validate that the scopes are well nested and
 /// parents fully contain their children. A list of scopes that are not well
-/// nested will result in an `Err` on construction.
+/// nested will result in an `Err` on construction, unless they are repaired
+/// using [`ScopeIndex::new_lenient`].
 ///
 /// # Examples
 ///
@@ -27,88 +34,601 @@ use indexmap::IndexSet;
 /// ```
 #[derive(Debug)]
 pub struct ScopeIndex {
-    names: IndexSet<String>,
-    /// Offset -> Index into `names` (or `u32::MAX` for `None`)
-    ranges: Vec<(u32, u32)>,
+    pub(crate) names: IndexSet<String>,
+    /// All the scopes, sorted by their start offset.
+    pub(crate) scopes: Vec<ScopeEntry>,
+    /// Offset -> Index into `scopes` (or `u32::MAX` for the global scope)
+    pub(crate) ranges: Vec<(u32, u32)>,
+    /// Indices into `scopes` of all the named scopes, sorted by their name
+    /// index, and by their start offset within each name.
+    ///
+    /// This is only built on the first lookup by name.
+    pub(crate) scopes_by_name: OnceLock<Vec<u32>>,
 }
 
 impl ScopeIndex {
     /// Creates a new Scope index from the given list of Scopes.
     #[tracing::instrument(level = "trace", name = "ScopeIndex::new", skip_all)]
-    pub fn new(mut scopes: Vec<(Range<u32>, Option<String>)>) -> Result<Self, ScopeIndexError> {
+    pub fn new(scopes: Vec<(Range<u32>, Option<String>)>) -> Result<Self, ScopeIndexError> {
+        let scopes = scopes
+            .into_iter()
+            .map(|(range, name)| (range, name, None))
+            .collect();
+        Self::with_minified_names(scopes)
+    }
+
+    /// Creates a new Scope index from the given list of Scopes, which have
+    /// both a name and a minified name.
+    ///
+    /// The minified names are stored alongside the names in the same
+    /// deduplicated name table, and can be accessed using
+    /// [`ScopeIndex::lookup_scope`], whereas [`ScopeIndex::lookup`] only
+    /// returns the primary name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::{ScopeIndex, ScopeLookupResult};
+    ///
+    /// let scopes = vec![(5..25, Some(String::from("original")), Some(String::from("a")))];
+    ///
+    /// let idx = ScopeIndex::with_minified_names(scopes).unwrap();
+    /// assert_eq!(idx.lookup(7), ScopeLookupResult::NamedScope("original"));
+    ///
+    /// let scope = idx.lookup_scope(7).unwrap();
+    /// assert_eq!(scope.name, Some("original"));
+    /// assert_eq!(scope.minified_name, Some("a"));
+    /// ```
+    #[tracing::instrument(level = "trace", name = "ScopeIndex::with_minified_names", skip_all)]
+    pub fn with_minified_names(
+        scopes: Vec<(Range<u32>, Option<String>, Option<String>)>,
+    ) -> Result<Self, ScopeIndexError> {
+        Self::build(scopes, None)
+    }
+
+    /// Creates a new Scope index from the given list of Scopes, repairing
+    /// scopes that are not well nested instead of failing.
+    ///
+    /// A scope that partially overlaps a preceding scope is clipped so that
+    /// it ends together with that preceding scope, and scopes that end before
+    /// they start are dropped. All the repairs are returned alongside the index.
+    ///
+    /// This is useful for scopes coming from other producers than
+    /// [`extract_scope_names`](crate::extract_scope_names), which might not
+    /// uphold the nesting guarantees.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::{ScopeIndex, ScopeLookupResult, ScopeRepair};
+    ///
+    /// let scopes = vec![
+    ///     (0..10, Some(String::from("first"))),
+    ///     (5..15, Some(String::from("second"))),
+    /// ];
+    ///
+    /// let (idx, repairs) = ScopeIndex::new_lenient(scopes).unwrap();
+    /// assert_eq!(idx.lookup(7), ScopeLookupResult::NamedScope("second"));
+    /// assert_eq!(idx.lookup(12), ScopeLookupResult::Unknown);
+    /// assert_eq!(
+    ///     repairs,
+    ///     [ScopeRepair::Clipped {
+    ///         range: 5..15,
+    ///         name: Some(String::from("second")),
+    ///         clipped: 5..10,
+    ///     }]
+    /// );
+    /// ```
+    #[tracing::instrument(level = "trace", name = "ScopeIndex::new_lenient", skip_all)]
+    pub fn new_lenient(
+        scopes: Vec<(Range<u32>, Option<String>)>,
+    ) -> Result<(Self, Vec<ScopeRepair>), ScopeIndexError> {
+        let scopes = scopes
+            .into_iter()
+            .map(|(range, name)| (range, name, None))
+            .collect();
+        let mut repairs = vec![];
+        let index = Self::build(scopes, Some(&mut repairs))?;
+        Ok((index, repairs))
+    }
+
+    /// Builds the index, recording repairs to `repairs` in lenient mode, or
+    /// failing on reversed or improperly nested scopes otherwise.
+    fn build(
+        mut scopes: Vec<(Range<u32>, Option<String>, Option<String>)>,
+        mut repairs: Option<&mut Vec<ScopeRepair>>,
+    ) -> Result<Self, ScopeIndexError> {
         let mut names = IndexSet::new();
+        let mut entries: Vec<ScopeEntry> = vec![];
         let mut ranges = vec![];
 
-        scopes.sort_by_key(|s| s.0.start);
+        // parents sort before children starting at the same offset
+        scopes.sort_by_key(|s| (s.0.start, Reverse(s.0.end)));
 
         let needs_zero = scopes.first().map(|s| s.0.start != 0).unwrap_or(false);
         if needs_zero {
             ranges.push((0, GLOBAL_SCOPE_SENTINEL));
         }
 
-        let mut stack: Vec<(Range<u32>, u32)> = vec![];
-
-        for (range, name) in scopes {
-            unwind_scope_stack(&mut ranges, &mut stack, range.clone())?;
-
-            let name_idx = match name {
-                Some(name) => names
-                    .insert_full(name)
-                    .0
-                    .try_into()
-                    .map_err(|_| ScopeIndexError(()))?,
-                None => ANONYMOUS_SCOPE_SENTINEL,
-            };
+        // a stack of indices into `entries`
+        let mut stack: Vec<u32> = vec![];
 
-            ranges.push((range.start, name_idx));
+        for (mut range, name, minified_name) in scopes {
+            if range.start > range.end {
+                match repairs.as_deref_mut() {
+                    Some(repairs) => {
+                        repairs.push(ScopeRepair::Dropped { range, name });
+                        continue;
+                    }
+                    None => return Err(ScopeIndexErrorKind::InvalidRange { range, name }.into()),
+                }
+            }
 
-            if let Some(last) = stack.last() {
-                if last.0.end == range.end {
-                    stack.pop();
+            let original_range = range.clone();
+            unwind_scope_stack(
+                &mut ranges,
+                &entries,
+                &names,
+                &mut stack,
+                (&mut range, name.as_deref()),
+                repairs.is_some(),
+            )?;
+            if let Some(repairs) = repairs.as_deref_mut() {
+                if range != original_range {
+                    repairs.push(ScopeRepair::Clipped {
+                        range: original_range,
+                        name: name.clone(),
+                        clipped: range.clone(),
+                    });
                 }
             }
-            stack.push((range, name_idx));
+
+            let name_idx = insert_name(&mut names, name)?;
+            let minified_name_idx = insert_name(&mut names, minified_name)?;
+            let scope_idx: u32 = entries
+                .len()
+                .try_into()
+                .ok()
+                .filter(|idx| *idx < ANONYMOUS_SCOPE_SENTINEL)
+                .ok_or(ScopeIndexErrorKind::TooManyScopes)?;
+
+            entries.push(ScopeEntry {
+                start: range.start,
+                end: range.end,
+                name_idx,
+                minified_name_idx,
+                parent_idx: stack.last().copied().unwrap_or(GLOBAL_SCOPE_SENTINEL),
+            });
+            push_range(&mut ranges, range.start, scope_idx);
+            stack.push(scope_idx);
         }
 
         // push end markers for the remaining stack
         while let Some(last) = stack.pop() {
             // push a new range of the parent
-            let name_idx = stack
-                .last()
-                .map(|prev| prev.1)
-                .unwrap_or(GLOBAL_SCOPE_SENTINEL);
-            ranges.push((last.0.end, name_idx));
+            let parent_idx = stack.last().copied().unwrap_or(GLOBAL_SCOPE_SENTINEL);
+            push_range(&mut ranges, entries[last as usize].end, parent_idx);
         }
 
-        Ok(Self { names, ranges })
+        Ok(Self {
+            names,
+            scopes: entries,
+            ranges,
+            scopes_by_name: OnceLock::new(),
+        })
+    }
+
+    /// Creates a new Scope index from the [`Scopes`] returned by
+    /// [`extract_scope_names`](crate::extract_scope_names).
+    ///
+    /// The [`ScopeName`](crate::ScopeName)s are converted to plain strings,
+    /// dropping their component ranges.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::{extract_scope_names, ScopeIndex, ScopeLookupResult};
+    ///
+    /// let src = "const arrowFnExpr = (a) => a; function namedFnDecl() {}";
+    /// let scopes = extract_scope_names(src).unwrap();
+    ///
+    /// let idx = ScopeIndex::from_scopes(scopes).unwrap();
+    /// assert_eq!(idx.lookup(25), ScopeLookupResult::NamedScope("arrowFnExpr"));
+    /// assert_eq!(idx.lookup(50), ScopeLookupResult::NamedScope("namedFnDecl"));
+    /// ```
+    pub fn from_scopes(scopes: Scopes) -> Result<Self, ScopeIndexError> {
+        let scopes = scopes
+            .into_iter()
+            .map(|(range, name)| (range, name.map(|name| name.to_string())))
+            .collect();
+        Self::new(scopes)
+    }
+
+    /// Creates a new Scope index from the [`Scopes`] of a minified source,
+    /// resolving their names to the original names using the given [`NameResolver`].
+    ///
+    /// The resolved original names are the primary names of the scopes, and the
+    /// minified names are stored alongside them, as in [`ScopeIndex::with_minified_names`].
+    ///
+    /// See [`NameResolver::resolve_scope`].
+    pub fn from_resolved_scopes<T>(
+        scopes: Scopes,
+        resolver: &NameResolver<'_, T>,
+    ) -> Result<Self, ScopeIndexError> {
+        let scopes = scopes
+            .into_iter()
+            .map(|(range, name)| {
+                let original = resolver
+                    .resolve_scope(range.clone(), name.as_ref())
+                    .name
+                    .map(|name| name.to_string());
+                (range, original, name.map(|name| name.to_string()))
+            })
+            .collect();
+        Self::with_minified_names(scopes)
+    }
+
+    /// Merges multiple indexes into one, rebasing each of them to start at
+    /// the given byte offset.
+    ///
+    /// This allows creating an index for the concatenation of multiple sources
+    /// without having to parse the concatenated source again. The names of all
+    /// the indexes are merged into one deduplicated name table.
+    ///
+    /// The rebased scopes have to be well nested, as in [`ScopeIndex::new`].
+    /// To rebase an index by a line offset instead, see [`SourceScopeIndex::merge`](crate::SourceScopeIndex::merge).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::{ScopeIndex, ScopeLookupResult};
+    ///
+    /// let first = ScopeIndex::new(vec![(0..10, Some(String::from("first")))]).unwrap();
+    /// let second = ScopeIndex::new(vec![(5..10, Some(String::from("second")))]).unwrap();
+    ///
+    /// // `second` was appended after the 20 bytes of the first source
+    /// let idx = ScopeIndex::merge([(0, &first), (20, &second)]).unwrap();
+    /// assert_eq!(idx.lookup(7), ScopeLookupResult::NamedScope("first"));
+    /// assert_eq!(idx.lookup(17), ScopeLookupResult::Unknown);
+    /// assert_eq!(idx.lookup(27), ScopeLookupResult::NamedScope("second"));
+    /// ```
+    #[tracing::instrument(level = "trace", name = "ScopeIndex::merge", skip_all)]
+    pub fn merge<'a>(
+        indexes: impl IntoIterator<Item = (u32, &'a ScopeIndex)>,
+    ) -> Result<Self, ScopeIndexError> {
+        let mut scopes = vec![];
+        for (offset, index) in indexes {
+            let get_name = |idx| index.names.get_index(idx as usize).cloned();
+            for scope in &index.scopes {
+                let range = rebase_offset(scope.start, offset)?..rebase_offset(scope.end, offset)?;
+                scopes.push((
+                    range,
+                    get_name(scope.name_idx),
+                    get_name(scope.minified_name_idx),
+                ));
+            }
+        }
+        Self::with_minified_names(scopes)
+    }
+
+    /// Moves all the scopes of this index by the given byte `offset`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::{ScopeIndex, ScopeLookupResult};
+    ///
+    /// let mut idx = ScopeIndex::new(vec![(0..10, Some(String::from("fn")))]).unwrap();
+    /// idx.rebase(100).unwrap();
+    /// assert_eq!(idx.lookup(5), ScopeLookupResult::Unknown);
+    /// assert_eq!(idx.lookup(105), ScopeLookupResult::NamedScope("fn"));
+    /// ```
+    pub fn rebase(&mut self, offset: u32) -> Result<(), ScopeIndexError> {
+        // check all the offsets first so the index stays intact on error
+        if let Some(last) = self.ranges.last() {
+            rebase_offset(last.0, offset)?;
+        }
+        for scope in &self.scopes {
+            rebase_offset(scope.end, offset)?;
+        }
+
+        for range in &mut self.ranges {
+            range.0 += offset;
+        }
+        for scope in &mut self.scopes {
+            scope.start += offset;
+            scope.end += offset;
+        }
+        Ok(())
+    }
+
+    /// Loads an index that was previously serialized using [`ScopeIndex::to_bytes`]
+    /// or [`ScopeIndex::write_to`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::{ScopeIndex, ScopeLookupResult};
+    ///
+    /// let scopes = vec![(5..25, Some(String::from("parent"))), (30..50, None)];
+    /// let buf = ScopeIndex::new(scopes).unwrap().to_bytes().unwrap();
+    ///
+    /// let idx = ScopeIndex::from_bytes(&buf).unwrap();
+    /// assert_eq!(idx.lookup(7), ScopeLookupResult::NamedScope("parent"));
+    /// assert_eq!(idx.lookup(40), ScopeLookupResult::AnonymousScope);
+    /// ```
+    #[tracing::instrument(level = "trace", name = "ScopeIndex::from_bytes", skip_all)]
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ScopeIndexFormatError> {
+        ScopeIndexView::parse(data)?.to_index()
+    }
+
+    /// Serializes this index into a versioned, endian-independent binary format.
+    ///
+    /// The index can be loaded again using [`ScopeIndex::from_bytes`].
+    ///
+    /// This fails if the tables of the index exceed the limits of the format,
+    /// like a string table larger than `u32::MAX` bytes.
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut buf = vec![];
+        self.write_to(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Writes this index in its binary format into the given `writer`.
+    ///
+    /// See [`ScopeIndex::to_bytes`].
+    pub fn write_to<W: io::Write>(&self, writer: W) -> io::Result<()> {
+        format::write(writer, &self.names, &self.scopes, &self.ranges)
     }
 
     /// Looks up the scope corresponding to the given `offset`.
     pub fn lookup(&self, offset: u32) -> ScopeLookupResult<'_> {
-        let range_idx = match self.ranges.binary_search_by_key(&offset, |r| r.0) {
-            Ok(idx) => idx,
-            Err(0) => 0, // this is pretty much unreachable since the first offset is 0
-            Err(idx) => idx - 1,
-        };
+        lookup(self, offset)
+    }
 
-        let name_idx = match self.ranges.get(range_idx) {
-            Some(r) => r.1,
-            None => return ScopeLookupResult::Unknown,
+    /// Looks up the innermost scope enclosing the given `offset`.
+    ///
+    /// In contrast to [`ScopeIndex::lookup`], this returns the full
+    /// [`IndexedScope`], including its range, nesting depth and id. Returns
+    /// `None` if the `offset` is not inside of any scope.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::ScopeIndex;
+    ///
+    /// let scopes = vec![
+    ///     (5..25, Some(String::from("parent"))),
+    ///     (10..15, Some(String::from("child"))),
+    /// ];
+    /// let idx = ScopeIndex::new(scopes).unwrap();
+    ///
+    /// let scope = idx.lookup_scope(12).unwrap();
+    /// assert_eq!(scope.name, Some("child"));
+    /// assert_eq!(scope.range, 10..15);
+    /// assert_eq!(scope.depth, 1);
+    /// // the offset relative to the start of the function
+    /// assert_eq!(12 - scope.range.start, 2);
+    ///
+    /// assert_eq!(idx.scope(scope.id), Some(scope));
+    /// assert_eq!(idx.lookup_scope(3), None);
+    /// ```
+    pub fn lookup_scope(&self, offset: u32) -> Option<IndexedScope<'_>> {
+        lookup_scope(self, offset)
+    }
+
+    /// Returns the scope with the given `id`, as returned from
+    /// [`ScopeIndex::lookup_scope`].
+    pub fn scope(&self, id: u32) -> Option<IndexedScope<'_>> {
+        get_scope(self, id)
+    }
+
+    /// Looks up the full stack of scopes enclosing the given `offset`.
+    ///
+    /// The scopes are returned from the innermost to the outermost scope. An
+    /// `offset` that is not inside of any scope yields an empty stack.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::ScopeIndex;
+    ///
+    /// let scopes = vec![
+    ///     (5..25, Some(String::from("parent"))),
+    ///     (10..15, None),
+    /// ];
+    /// let idx = ScopeIndex::new(scopes).unwrap();
+    ///
+    /// let stack: Vec<_> = idx
+    ///     .lookup_stack(12)
+    ///     .map(|scope| (scope.range, scope.name))
+    ///     .collect();
+    /// assert_eq!(stack, [(10..15, None), (5..25, Some("parent"))]);
+    /// assert_eq!(idx.lookup_stack(3).count(), 0);
+    /// ```
+    pub fn lookup_stack(&self, offset: u32) -> impl Iterator<Item = IndexedScope<'_>> + '_ {
+        scope_stack(self, offset)
+    }
+
+    /// Returns all the scopes intersecting the given byte `range`.
+    ///
+    /// This includes the scopes enclosing the start of the `range`, as well as
+    /// all the scopes starting inside of it. Scopes are returned in order of
+    /// their starting offsets, so parents always come before their children.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::ScopeIndex;
+    ///
+    /// let scopes = vec![
+    ///     (5..25, Some(String::from("parent"))),
+    ///     (10..15, Some(String::from("child"))),
+    ///     (20..25, Some(String::from("child2"))),
+    ///     (30..50, None),
+    /// ];
+    /// let idx = ScopeIndex::new(scopes).unwrap();
+    ///
+    /// let scopes: Vec<_> = idx
+    ///     .scopes_in_range(12..22)
+    ///     .map(|scope| (scope.range, scope.depth))
+    ///     .collect();
+    /// assert_eq!(scopes, [(5..25, 0), (10..15, 1), (20..25, 1)]);
+    /// ```
+    pub fn scopes_in_range(
+        &self,
+        range: Range<u32>,
+    ) -> impl Iterator<Item = IndexedScope<'_>> + '_ {
+        scopes_in_range(self, range)
+    }
+
+    /// Returns all the scopes with the given `name`.
+    ///
+    /// Scopes are returned in order of their starting offsets. Only the
+    /// primary name of each scope is matched, not its minified name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::ScopeIndex;
+    ///
+    /// let scopes = vec![
+    ///     (5..25, Some(String::from("handleSubmit"))),
+    ///     (10..15, Some(String::from("handleChange"))),
+    ///     (30..50, Some(String::from("handleSubmit"))),
+    /// ];
+    /// let idx = ScopeIndex::new(scopes).unwrap();
+    ///
+    /// let ranges: Vec<_> = idx.scopes_by_name("handleSubmit").map(|s| s.range).collect();
+    /// assert_eq!(ranges, [5..25, 30..50]);
+    /// assert_eq!(idx.scopes_by_name("handle").count(), 0);
+    /// ```
+    pub fn scopes_by_name(&self, name: &str) -> impl Iterator<Item = IndexedScope<'_>> + '_ {
+        let scope_ids = match self.names.get_index_of(name) {
+            Some(name_idx) => self.scope_ids_by_name_idx(name_idx as u32),
+            None => &[],
         };
+        scope_ids
+            .iter()
+            .map(move |&idx| indexed_scope(self, idx, scope_depth(self, idx)))
+    }
 
-        self.resolve_name(name_idx)
+    /// Returns all the scopes with a name starting with `prefix`.
+    ///
+    /// Scopes are returned in order of their starting offsets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::ScopeIndex;
+    ///
+    /// let scopes = vec![
+    ///     (5..25, Some(String::from("handleSubmit"))),
+    ///     (10..15, Some(String::from("handleChange"))),
+    ///     (30..50, Some(String::from("render"))),
+    /// ];
+    /// let idx = ScopeIndex::new(scopes).unwrap();
+    ///
+    /// let names: Vec<_> = idx.scopes_by_name_prefix("handle").map(|s| s.name).collect();
+    /// assert_eq!(names, [Some("handleSubmit"), Some("handleChange")]);
+    /// ```
+    pub fn scopes_by_name_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = IndexedScope<'a>> + 'a {
+        // match the prefix once per distinct name instead of once per scope
+        let mut scope_ids: Vec<u32> = (0..self.names.len() as u32)
+            .filter(|idx| self.names[*idx as usize].starts_with(prefix))
+            .flat_map(|idx| self.scope_ids_by_name_idx(idx))
+            .copied()
+            .collect();
+        scope_ids.sort_unstable();
+        scope_ids
+            .into_iter()
+            .map(move |idx| indexed_scope(self, idx, scope_depth(self, idx)))
     }
 
-    fn resolve_name(&self, name_idx: u32) -> ScopeLookupResult<'_> {
-        if name_idx == GLOBAL_SCOPE_SENTINEL {
-            ScopeLookupResult::Unknown
-        } else if name_idx == ANONYMOUS_SCOPE_SENTINEL {
-            ScopeLookupResult::AnonymousScope
-        } else {
-            match self.names.get_index(name_idx as usize) {
-                Some(name) => ScopeLookupResult::NamedScope(name.as_str()),
-                None => ScopeLookupResult::Unknown,
-            }
+    /// Returns the indices into `scopes` of all the scopes named `name_idx`,
+    /// sorted by their start offset.
+    fn scope_ids_by_name_idx(&self, name_idx: u32) -> &[u32] {
+        let scope_ids = self.scopes_by_name.get_or_init(|| {
+            let mut scope_ids: Vec<u32> = (0..self.scopes.len() as u32)
+                .filter(|idx| self.scopes[*idx as usize].name_idx != ANONYMOUS_SCOPE_SENTINEL)
+                .collect();
+            // the sort is stable, so the scopes stay sorted by start offset
+            scope_ids.sort_by_key(|idx| self.scopes[*idx as usize].name_idx);
+            scope_ids
+        });
+        let name_of = |idx: &u32| self.scopes[*idx as usize].name_idx;
+        let start = scope_ids.partition_point(|idx| name_of(idx) < name_idx);
+        let end = scope_ids.partition_point(|idx| name_of(idx) <= name_idx);
+        &scope_ids[start..end]
+    }
+
+    /// Returns an estimate of the heap memory used by this index, in bytes.
+    ///
+    /// This includes the allocated capacity of all the tables, and the
+    /// bookkeeping of the deduplicated name table.
+    pub fn heap_size(&self) -> usize {
+        use std::mem::size_of;
+
+        // every name has an entry holding its hash, and a slot in the hash table
+        let name_entry_size = size_of::<String>() + 2 * size_of::<usize>() + 1;
+        let names = self.names.capacity() * name_entry_size
+            + self.names.iter().map(String::capacity).sum::<usize>();
+        let scopes = self.scopes.capacity() * size_of::<ScopeEntry>();
+        let ranges = self.ranges.capacity() * size_of::<(u32, u32)>();
+        let scopes_by_name = self
+            .scopes_by_name
+            .get()
+            .map_or(0, |ids| ids.capacity() * size_of::<u32>());
+
+        names + scopes + ranges + scopes_by_name
+    }
+
+    /// Returns statistics about the scopes in this index.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::ScopeIndex;
+    ///
+    /// let scopes = vec![
+    ///     (5..25, Some(String::from("parent"))),
+    ///     (10..15, Some(String::from("parent"))),
+    ///     (11..12, None),
+    ///     (30..50, None),
+    /// ];
+    /// let idx = ScopeIndex::new(scopes).unwrap();
+    ///
+    /// let stats = idx.stats();
+    /// assert_eq!(stats.num_scopes, 4);
+    /// assert_eq!(stats.num_names, 1);
+    /// assert_eq!(stats.num_anonymous_scopes, 2);
+    /// assert_eq!(stats.anonymous_ratio(), 0.5);
+    /// assert_eq!(stats.max_depth, 2);
+    /// ```
+    pub fn stats(&self) -> ScopeIndexStats {
+        // parents always come before their children
+        let mut depths: Vec<u32> = Vec::with_capacity(self.scopes.len());
+        for scope in &self.scopes {
+            let depth = match scope.parent_idx {
+                GLOBAL_SCOPE_SENTINEL => 0,
+                parent_idx => depths[parent_idx as usize] + 1,
+            };
+            depths.push(depth);
+        }
+
+        ScopeIndexStats {
+            heap_size: self.heap_size(),
+            num_scopes: self.scopes.len(),
+            num_names: self.names.len(),
+            num_anonymous_scopes: self
+                .scopes
+                .iter()
+                .filter(|scope| scope.name_idx == ANONYMOUS_SCOPE_SENTINEL)
+                .count(),
+            max_depth: depths.into_iter().max().unwrap_or(0),
         }
     }
 
@@ -117,12 +637,122 @@ impl ScopeIndex {
     ///
     /// Scopes are returned in order of their starting offsets.
     pub fn iter(&self) -> impl Iterator<Item = (u32, ScopeLookupResult<'_>)> {
-        self.ranges.iter().map(|r| (r.0, self.resolve_name(r.1)))
+        self.ranges
+            .iter()
+            .map(move |r| (r.0, resolve_scope_name(self, r.1)))
+    }
+}
+
+impl TryFrom<Scopes> for ScopeIndex {
+    type Error = ScopeIndexError;
+
+    fn try_from(scopes: Scopes) -> Result<Self, Self::Error> {
+        Self::from_scopes(scopes)
+    }
+}
+
+impl<'data> ScopeTables<'data> for &'data ScopeIndex {
+    fn num_ranges(&self) -> usize {
+        self.ranges.len()
+    }
+
+    fn num_scopes(&self) -> usize {
+        self.scopes.len()
+    }
+
+    fn range(&self, idx: usize) -> (u32, u32) {
+        self.ranges[idx]
+    }
+
+    fn scope(&self, scope_idx: u32) -> ScopeEntry {
+        self.scopes[scope_idx as usize]
+    }
+
+    fn name(&self, name_idx: u32) -> Option<&'data str> {
+        self.names.get_index(name_idx as usize).map(String::as_str)
+    }
+}
+
+/// Statistics about a [`ScopeIndex`], as returned by [`ScopeIndex::stats`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct ScopeIndexStats {
+    /// The estimated heap memory used by the index, see [`ScopeIndex::heap_size`].
+    pub heap_size: usize,
+    /// The number of scopes.
+    pub num_scopes: usize,
+    /// The number of unique names, including minified names.
+    pub num_names: usize,
+    /// The number of scopes without a name.
+    pub num_anonymous_scopes: usize,
+    /// The maximum nesting depth of all the scopes, see [`IndexedScope::depth`].
+    pub max_depth: u32,
+}
+
+impl ScopeIndexStats {
+    /// The ratio of anonymous scopes to all scopes, between `0.0` and `1.0`.
+    ///
+    /// This is `0.0` for an empty index.
+    pub fn anonymous_ratio(&self) -> f64 {
+        match self.num_scopes {
+            0 => 0.0,
+            num_scopes => self.num_anonymous_scopes as f64 / num_scopes as f64,
+        }
+    }
+}
+
+/// A scope inside of a [`ScopeIndex`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct IndexedScope<'data> {
+    /// The id of the scope.
+    ///
+    /// The id is the position of the scope when sorted by start offset. It
+    /// uniquely identifies the scope within its [`ScopeIndex`], and stays the
+    /// same when serializing the index.
+    pub id: u32,
+    /// The byte range of the scope.
+    pub range: Range<u32>,
+    /// The nesting depth of the scope, `0` for top-level scopes.
+    pub depth: u32,
+    /// The name of the scope, or `None` for anonymous scopes.
+    pub name: Option<&'data str>,
+    /// The minified name of the scope.
+    ///
+    /// This is only available for indexes created with
+    /// [`ScopeIndex::with_minified_names`] or [`ScopeIndex::from_resolved_scopes`].
+    pub minified_name: Option<&'data str>,
+}
+
+impl<'data> IndexedScope<'data> {
+    /// Returns the name of this scope as a [`ScopeLookupResult`].
+    pub fn lookup_result(&self) -> ScopeLookupResult<'data> {
+        match self.name {
+            Some(name) => ScopeLookupResult::NamedScope(name),
+            None => ScopeLookupResult::AnonymousScope,
+        }
     }
 }
 
 /// The Result of a Scope lookup.
+///
+/// With the `serde` feature enabled, lookup results are serialized as objects
+/// tagged by their `kind`:
+///
+/// ```json
+/// {"kind": "named_scope", "name": "parent"}
+/// {"kind": "anonymous_scope"}
+/// {"kind": "unknown"}
+/// ```
+///
+/// Use [`OwnedScopeLookupResult`] to deserialize them again.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(tag = "kind", content = "name", rename_all = "snake_case")
+)]
 pub enum ScopeLookupResult<'data> {
     /// A named function scope.
     NamedScope(&'data str),
@@ -134,24 +764,301 @@ pub enum ScopeLookupResult<'data> {
     Unknown,
 }
 
-/// Given a `stack` of ranges, this pushes all entries on the stack
-/// to `ranges` that end before `offset`, and ensures well-nestedness.
+impl ScopeLookupResult<'_> {
+    /// Converts this lookup result into an [`OwnedScopeLookupResult`] which
+    /// does not borrow from the [`ScopeIndex`].
+    pub fn into_owned(self) -> OwnedScopeLookupResult {
+        match self {
+            Self::NamedScope(name) => OwnedScopeLookupResult::NamedScope(name.to_owned()),
+            Self::AnonymousScope => OwnedScopeLookupResult::AnonymousScope,
+            Self::Unknown => OwnedScopeLookupResult::Unknown,
+        }
+    }
+}
+
+/// An owned version of [`ScopeLookupResult`].
+///
+/// This has the same serialized representation as [`ScopeLookupResult`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "kind", content = "name", rename_all = "snake_case")
+)]
+pub enum OwnedScopeLookupResult {
+    /// A named function scope.
+    NamedScope(String),
+    /// An anonymous function scope for which no name was inferred.
+    AnonymousScope,
+    /// The lookup did not result in any scope match.
+    Unknown,
+}
+
+impl OwnedScopeLookupResult {
+    /// Borrows this as a [`ScopeLookupResult`].
+    pub fn as_lookup_result(&self) -> ScopeLookupResult<'_> {
+        match self {
+            Self::NamedScope(name) => ScopeLookupResult::NamedScope(name),
+            Self::AnonymousScope => ScopeLookupResult::AnonymousScope,
+            Self::Unknown => ScopeLookupResult::Unknown,
+        }
+    }
+}
+
+impl From<ScopeLookupResult<'_>> for OwnedScopeLookupResult {
+    fn from(result: ScopeLookupResult<'_>) -> Self {
+        result.into_owned()
+    }
+}
+
+/// A single scope, as stored inside of a [`ScopeIndex`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct ScopeEntry {
+    pub(crate) start: u32,
+    pub(crate) end: u32,
+    /// Index into the names (or `u32::MAX - 1` for anonymous scopes)
+    pub(crate) name_idx: u32,
+    /// Index into the names (or `u32::MAX - 1` if there is no minified name)
+    pub(crate) minified_name_idx: u32,
+    /// Index of the parent scope (or `u32::MAX` for the global scope)
+    pub(crate) parent_idx: u32,
+}
+
+/// The tables making up a [`ScopeIndex`].
+///
+/// This allows implementing lookups once for both the owned [`ScopeIndex`]
+/// and the zero-copy [`ScopeIndexView`]. All indices passed to these methods
+/// are expected to be in bounds.
+pub(crate) trait ScopeTables<'data>: Copy {
+    /// The number of entries in the ranges table.
+    fn num_ranges(&self) -> usize;
+    /// The number of entries in the scopes table.
+    fn num_scopes(&self) -> usize;
+    /// Returns the `(offset, scope_idx)` entry of the ranges table at `idx`.
+    fn range(&self, idx: usize) -> (u32, u32);
+    /// Returns the scope at `scope_idx`.
+    fn scope(&self, scope_idx: u32) -> ScopeEntry;
+    /// Returns the name at `name_idx`.
+    fn name(&self, name_idx: u32) -> Option<&'data str>;
+}
+
+/// Finds the innermost scope containing `offset`.
+///
+/// Returns the index of that scope, or `GLOBAL_SCOPE_SENTINEL`.
+fn find_scope<'data>(tables: impl ScopeTables<'data>, offset: u32) -> u32 {
+    // find the last range starting at or before `offset`
+    let (mut low, mut high) = (0, tables.num_ranges());
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if tables.range(mid).0 <= offset {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    match low {
+        0 => GLOBAL_SCOPE_SENTINEL,
+        idx => tables.range(idx - 1).1,
+    }
+}
+
+pub(crate) fn lookup<'data>(
+    tables: impl ScopeTables<'data>,
+    offset: u32,
+) -> ScopeLookupResult<'data> {
+    resolve_scope_name(tables, find_scope(tables, offset))
+}
+
+pub(crate) fn lookup_scope<'data>(
+    tables: impl ScopeTables<'data>,
+    offset: u32,
+) -> Option<IndexedScope<'data>> {
+    let scope_idx = find_scope(tables, offset);
+    (scope_idx != GLOBAL_SCOPE_SENTINEL)
+        .then(|| indexed_scope(tables, scope_idx, scope_depth(tables, scope_idx)))
+}
+
+pub(crate) fn get_scope<'data>(
+    tables: impl ScopeTables<'data>,
+    scope_idx: u32,
+) -> Option<IndexedScope<'data>> {
+    ((scope_idx as usize) < tables.num_scopes())
+        .then(|| indexed_scope(tables, scope_idx, scope_depth(tables, scope_idx)))
+}
+
+pub(crate) fn scope_stack<'data, T: ScopeTables<'data>>(
+    tables: T,
+    offset: u32,
+) -> impl Iterator<Item = IndexedScope<'data>> {
+    let mut scope_idx = find_scope(tables, offset);
+    let mut depth = scope_depth(tables, scope_idx);
+    std::iter::from_fn(move || {
+        if scope_idx == GLOBAL_SCOPE_SENTINEL {
+            return None;
+        }
+        let scope = indexed_scope(tables, scope_idx, depth);
+        scope_idx = tables.scope(scope_idx).parent_idx;
+        depth = depth.saturating_sub(1);
+
+        Some(scope)
+    })
+}
+
+pub(crate) fn scopes_in_range<'data, T: ScopeTables<'data>>(
+    tables: T,
+    range: Range<u32>,
+) -> impl Iterator<Item = IndexedScope<'data>> {
+    // all the scopes enclosing the start of the range, outermost first
+    let mut enclosing: Vec<_> = match range.start < range.end {
+        true => scope_stack(tables, range.start).collect(),
+        false => vec![],
+    };
+    enclosing.reverse();
+
+    // find the first scope starting after the start of the range
+    let (mut low, mut high) = (0, tables.num_scopes());
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if tables.scope(mid as u32).start <= range.start {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    let starting_inside = (low..tables.num_scopes())
+        .map(|idx| idx as u32)
+        .take_while(move |idx| tables.scope(*idx).start < range.end)
+        .map(move |idx| indexed_scope(tables, idx, scope_depth(tables, idx)));
+
+    enclosing.into_iter().chain(starting_inside)
+}
+
+/// Returns all the scopes for which `matches` returns `true`, in order of
+/// their starting offsets.
+pub(crate) fn scopes_matching<'data, T: ScopeTables<'data>>(
+    tables: T,
+    matches: impl Fn(&ScopeEntry) -> bool,
+) -> impl Iterator<Item = IndexedScope<'data>> {
+    (0..tables.num_scopes() as u32)
+        .filter(move |idx| matches(&tables.scope(*idx)))
+        .map(move |idx| indexed_scope(tables, idx, scope_depth(tables, idx)))
+}
+
+/// Computes the nesting depth of the scope at `scope_idx` by walking up its
+/// parents.
+fn scope_depth<'data>(tables: impl ScopeTables<'data>, mut scope_idx: u32) -> u32 {
+    let mut depth = 0u32;
+    while scope_idx != GLOBAL_SCOPE_SENTINEL {
+        scope_idx = tables.scope(scope_idx).parent_idx;
+        depth += 1;
+    }
+    depth.saturating_sub(1)
+}
+
+fn indexed_scope<'data>(
+    tables: impl ScopeTables<'data>,
+    scope_idx: u32,
+    depth: u32,
+) -> IndexedScope<'data> {
+    let scope = tables.scope(scope_idx);
+    IndexedScope {
+        id: scope_idx,
+        range: scope.start..scope.end,
+        depth,
+        name: tables.name(scope.name_idx),
+        minified_name: tables.name(scope.minified_name_idx),
+    }
+}
+
+/// Resolves the name of the scope at `scope_idx`, which may be the global
+/// scope sentinel.
+pub(crate) fn resolve_scope_name<'data>(
+    tables: impl ScopeTables<'data>,
+    scope_idx: u32,
+) -> ScopeLookupResult<'data> {
+    if scope_idx == GLOBAL_SCOPE_SENTINEL {
+        return ScopeLookupResult::Unknown;
+    }
+    let name_idx = tables.scope(scope_idx).name_idx;
+    if name_idx == ANONYMOUS_SCOPE_SENTINEL {
+        ScopeLookupResult::AnonymousScope
+    } else {
+        match tables.name(name_idx) {
+            Some(name) => ScopeLookupResult::NamedScope(name),
+            None => ScopeLookupResult::Unknown,
+        }
+    }
+}
+
+/// Pushes a new `(offset, scope_idx)` entry to `ranges`.
+///
+/// This replaces the last entry if it starts at the same `offset`, which
+/// happens when a scope ends exactly where the next one starts.
+fn push_range(ranges: &mut Vec<(u32, u32)>, offset: u32, scope_idx: u32) {
+    match ranges.last_mut() {
+        Some(last) if last.0 == offset => last.1 = scope_idx,
+        _ => ranges.push((offset, scope_idx)),
+    }
+}
+
+/// Adds `by` to the given scope `offset`, failing on overflow.
+fn rebase_offset(offset: u32, by: u32) -> Result<u32, ScopeIndexError> {
+    offset
+        .checked_add(by)
+        .ok_or_else(|| ScopeIndexErrorKind::InvalidOffset.into())
+}
+
+/// Inserts the given `name` into `names`, returning its index or the anonymous
+/// scope sentinel.
+fn insert_name(names: &mut IndexSet<String>, name: Option<String>) -> Result<u32, ScopeIndexError> {
+    match name {
+        Some(name) => names
+            .insert_full(name)
+            .0
+            .try_into()
+            .ok()
+            .filter(|idx| *idx < ANONYMOUS_SCOPE_SENTINEL)
+            .ok_or_else(|| ScopeIndexErrorKind::TooManyNames.into()),
+        None => Ok(ANONYMOUS_SCOPE_SENTINEL),
+    }
+}
+
+/// Given a `stack` of scopes, this pushes all entries on the stack
+/// to `ranges` that end before the start of `scope`, and ensures well-nestedness.
+///
+/// In `lenient` mode, a `scope` that is not well nested is clipped to end
+/// together with its parent, instead of returning an error.
 fn unwind_scope_stack(
     ranges: &mut Vec<(u32, u32)>,
-    stack: &mut Vec<(Range<u32>, u32)>,
-    range: Range<u32>,
+    entries: &[ScopeEntry],
+    names: &IndexSet<String>,
+    stack: &mut Vec<u32>,
+    scope: (&mut Range<u32>, Option<&str>),
+    lenient: bool,
 ) -> Result<(), ScopeIndexError> {
+    let (range, name) = scope;
     while let Some(last) = stack.pop() {
+        let last_scope = entries[last as usize];
         // push a new range of the parent
-        if last.0.end <= range.start {
-            let name_idx = stack
-                .last()
-                .map(|prev| prev.1)
-                .unwrap_or(GLOBAL_SCOPE_SENTINEL);
-            ranges.push((last.0.end, name_idx));
-        } else if last.0.end < range.end {
+        if last_scope.end <= range.start {
+            let parent_idx = stack.last().copied().unwrap_or(GLOBAL_SCOPE_SENTINEL);
+            push_range(ranges, last_scope.end, parent_idx);
+        } else if last_scope.end < range.end && lenient {
+            range.end = last_scope.end;
+            stack.push(last);
+            return Ok(());
+        } else if last_scope.end < range.end {
             // we have an overlap and improper nesting
-            return Err(ScopeIndexError(()));
+            let get_name = |idx| names.get_index(idx as usize).cloned();
+            return Err(ScopeIndexErrorKind::InvalidNesting {
+                first: last_scope.start..last_scope.end,
+                first_name: get_name(last_scope.name_idx),
+                second: range.clone(),
+                second_name: name.map(String::from),
+            }
+            .into());
         } else {
             // re-push to the stack, as it is still our same parent
             stack.push(last);
@@ -164,15 +1071,113 @@ fn unwind_scope_stack(
 pub(crate) const GLOBAL_SCOPE_SENTINEL: u32 = u32::MAX;
 pub(crate) const ANONYMOUS_SCOPE_SENTINEL: u32 = u32::MAX - 1;
 
+/// A repair done by [`ScopeIndex::new_lenient`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ScopeRepair {
+    /// The scope partially overlapped a preceding scope, and was clipped to
+    /// end together with it.
+    Clipped {
+        /// The original range of the scope.
+        range: Range<u32>,
+        /// The name of the scope.
+        name: Option<String>,
+        /// The clipped range of the scope.
+        clipped: Range<u32>,
+    },
+    /// The scope ended before it started, and was dropped.
+    Dropped {
+        /// The range of the scope.
+        range: Range<u32>,
+        /// The name of the scope.
+        name: Option<String>,
+    },
+}
+
+/// The kind of a [`ScopeIndexError`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ScopeIndexErrorKind {
+    /// Two scopes partially overlap, so neither one fully contains the other.
+    InvalidNesting {
+        /// The range of the scope starting first.
+        first: Range<u32>,
+        /// The name of the scope starting first.
+        first_name: Option<String>,
+        /// The range of the scope starting second.
+        second: Range<u32>,
+        /// The name of the scope starting second.
+        second_name: Option<String>,
+    },
+    /// A scope ends before it starts.
+    InvalidRange {
+        /// The range of the scope.
+        range: Range<u32>,
+        /// The name of the scope.
+        name: Option<String>,
+    },
+    /// The number of unique names exceeds the range of a `u32`.
+    TooManyNames,
+    /// The number of scopes exceeds the range of a `u32`.
+    TooManyScopes,
+    /// A scope could not be rebased, because its new offset would exceed the
+    /// range of a `u32`, or lie outside of the source.
+    InvalidOffset,
+}
+
 /// An Error that can happen when building a [`ScopeIndex`].
 #[derive(Debug)]
-pub struct ScopeIndexError(());
+pub struct ScopeIndexError {
+    kind: ScopeIndexErrorKind,
+}
+
+impl ScopeIndexError {
+    /// The kind of this error.
+    pub fn kind(&self) -> &ScopeIndexErrorKind {
+        &self.kind
+    }
+}
+
+impl From<ScopeIndexErrorKind> for ScopeIndexError {
+    fn from(kind: ScopeIndexErrorKind) -> Self {
+        Self { kind }
+    }
+}
 
 impl std::error::Error for ScopeIndexError {}
 
 impl std::fmt::Display for ScopeIndexError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str("source could not be converted to source context")
+        fn write_scope(
+            f: &mut std::fmt::Formatter<'_>,
+            range: &Range<u32>,
+            name: &Option<String>,
+        ) -> std::fmt::Result {
+            match name {
+                Some(name) => write!(f, "scope `{name}` ({range:?})"),
+                None => write!(f, "anonymous scope ({range:?})"),
+            }
+        }
+
+        match &self.kind {
+            ScopeIndexErrorKind::InvalidNesting {
+                first,
+                first_name,
+                second,
+                second_name,
+            } => {
+                write_scope(f, first, first_name)?;
+                f.write_str(" partially overlaps ")?;
+                write_scope(f, second, second_name)
+            }
+            ScopeIndexErrorKind::InvalidRange { range, name } => {
+                write_scope(f, range, name)?;
+                f.write_str(" ends before it starts")
+            }
+            ScopeIndexErrorKind::TooManyNames => f.write_str("too many scope names"),
+            ScopeIndexErrorKind::TooManyScopes => f.write_str("too many scopes"),
+            ScopeIndexErrorKind::InvalidOffset => f.write_str("invalid rebased scope offset"),
+        }
     }
 }
 
@@ -184,6 +1189,49 @@ mod tests {
     fn invalid_nesting() {
         let scopes = vec![(0..10, None), (5..15, None)];
         assert!(ScopeIndex::new(scopes).is_err());
+
+        let scopes = vec![
+            (0..30, Some(String::from("parent"))),
+            (5..15, Some(String::from("child"))),
+            (10..20, None),
+        ];
+        let err = ScopeIndex::new(scopes).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ScopeIndexErrorKind::InvalidNesting {
+                first: 5..15,
+                first_name: Some(String::from("child")),
+                second: 10..20,
+                second_name: None,
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "scope `child` (5..15) partially overlaps anonymous scope (10..20)"
+        );
+    }
+
+    #[test]
+    fn invalid_range() {
+        let reversed = Range { start: 25, end: 22 };
+        let scopes = vec![
+            (0..30, Some(String::from("p"))),
+            (reversed.clone(), Some(String::from("r"))),
+        ];
+        let err = ScopeIndex::new(scopes.clone()).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ScopeIndexErrorKind::InvalidRange {
+                range: reversed,
+                name: Some(String::from("r")),
+            }
+        );
+        assert_eq!(err.to_string(), "scope `r` (25..22) ends before it starts");
+
+        // lenient construction drops the scope, and the index round-trips
+        let (idx, _) = ScopeIndex::new_lenient(scopes).unwrap();
+        let loaded = ScopeIndex::from_bytes(&idx.to_bytes().unwrap()).unwrap();
+        assert_eq!(loaded.lookup(23), ScopeLookupResult::NamedScope("p"));
     }
 
     #[test]
@@ -206,4 +1254,267 @@ mod tests {
         assert_eq!(idx.lookup(30), ScopeLookupResult::AnonymousScope);
         assert_eq!(idx.lookup(50), ScopeLookupResult::Unknown);
     }
+
+    #[test]
+    fn lenient_nesting() {
+        let reversed = Range { start: 25, end: 22 };
+        let scopes = vec![
+            (0..30, Some(String::from("parent"))),
+            (5..15, Some(String::from("child"))),
+            (10..20, None),
+            (reversed.clone(), Some(String::from("reversed"))),
+            (26..40, Some(String::from("sibling"))),
+        ];
+
+        let (idx, repairs) = ScopeIndex::new_lenient(scopes).unwrap();
+        assert_eq!(
+            repairs,
+            [
+                ScopeRepair::Clipped {
+                    range: 10..20,
+                    name: None,
+                    clipped: 10..15,
+                },
+                ScopeRepair::Dropped {
+                    range: reversed,
+                    name: Some(String::from("reversed")),
+                },
+                ScopeRepair::Clipped {
+                    range: 26..40,
+                    name: Some(String::from("sibling")),
+                    clipped: 26..30,
+                },
+            ]
+        );
+
+        let stack = |offset| {
+            idx.lookup_stack(offset)
+                .map(|s| (s.range, s.name))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            stack(12),
+            [
+                (10..15, None),
+                (5..15, Some("child")),
+                (0..30, Some("parent"))
+            ]
+        );
+        assert_eq!(stack(17), [(0..30, Some("parent"))]);
+        assert_eq!(
+            stack(27),
+            [(26..30, Some("sibling")), (0..30, Some("parent"))]
+        );
+        assert_eq!(stack(35), []);
+
+        // well nested scopes are not changed
+        let scopes = vec![(0..30, None), (5..15, None)];
+        let (_idx, repairs) = ScopeIndex::new_lenient(scopes).unwrap();
+        assert!(repairs.is_empty());
+    }
+
+    #[test]
+    fn scope_stack() {
+        let scopes = vec![
+            (10..15, Some(String::from("child"))),
+            (5..25, Some(String::from("parent"))),
+            (10..12, None),
+            (30..50, None),
+        ];
+
+        let idx = ScopeIndex::new(scopes).unwrap();
+        let stack = |offset| {
+            idx.lookup_stack(offset)
+                .map(|s| (s.range, s.name))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(stack(3), []);
+        assert_eq!(stack(7), [(5..25, Some("parent"))]);
+        assert_eq!(
+            stack(11),
+            [
+                (10..12, None),
+                (10..15, Some("child")),
+                (5..25, Some("parent"))
+            ]
+        );
+        assert_eq!(
+            stack(12),
+            [(10..15, Some("child")), (5..25, Some("parent"))]
+        );
+        assert_eq!(stack(40), [(30..50, None)]);
+        assert_eq!(stack(50), []);
+
+        let depths: Vec<_> = idx.lookup_stack(11).map(|s| (s.id, s.depth)).collect();
+        assert_eq!(depths, [(2, 2), (1, 1), (0, 0)]);
+        assert_eq!(idx.lookup_scope(11), idx.lookup_stack(11).next());
+        assert_eq!(idx.scope(3).map(|s| s.range), Some(30..50));
+        assert_eq!(idx.scope(4), None);
+    }
+
+    #[test]
+    fn scopes_in_range() {
+        let scopes = vec![
+            (5..25, Some(String::from("parent"))),
+            (10..15, Some(String::from("child"))),
+            (20..25, Some(String::from("child2"))),
+            (30..50, None),
+        ];
+
+        let idx = ScopeIndex::new(scopes).unwrap();
+        let in_range = |range| idx.scopes_in_range(range).map(|s| s.id).collect::<Vec<_>>();
+
+        assert!(in_range(0..5).is_empty());
+        assert_eq!(in_range(0..6), [0]);
+        assert_eq!(in_range(0..100), [0, 1, 2, 3]);
+        assert_eq!(in_range(12..13), [0, 1]);
+        assert_eq!(in_range(15..20), [0]);
+        assert_eq!(in_range(15..21), [0, 2]);
+        assert_eq!(in_range(24..31), [0, 2, 3]);
+        assert!(in_range(25..30).is_empty());
+        assert!(in_range(12..12).is_empty());
+    }
+
+    #[test]
+    fn scopes_by_name() {
+        let scopes = vec![
+            (5..25, Some(String::from("handleSubmit"))),
+            (10..15, None),
+            (30..50, Some(String::from("handleChange"))),
+            (60..70, Some(String::from("handleSubmit"))),
+        ];
+        let idx = ScopeIndex::with_minified_names(
+            scopes
+                .into_iter()
+                .map(|(range, name)| (range, name, Some(String::from("a"))))
+                .collect(),
+        )
+        .unwrap();
+
+        let by_name = |name| idx.scopes_by_name(name).map(|s| s.id).collect::<Vec<_>>();
+        assert_eq!(by_name("handleSubmit"), [0, 3]);
+        assert_eq!(by_name("handleChange"), [2]);
+        // minified names are not matched
+        assert!(by_name("a").is_empty());
+        assert!(by_name("handle").is_empty());
+
+        let by_prefix = |prefix| {
+            idx.scopes_by_name_prefix(prefix)
+                .map(|s| s.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(by_prefix("handle"), [0, 2, 3]);
+        assert_eq!(by_prefix("handleC"), [2]);
+        assert_eq!(by_prefix(""), [0, 2, 3]);
+        assert!(by_prefix("a").is_empty());
+    }
+
+    #[test]
+    fn scopes_by_duplicate_name() {
+        let scopes = vec![
+            (0..100, Some(String::from("render"))),
+            (10..20, Some(String::from("update"))),
+            (30..60, Some(String::from("render"))),
+            (35..40, Some(String::from("update"))),
+            (45..50, None),
+            (70..80, Some(String::from("render"))),
+        ];
+        let idx = ScopeIndex::new(scopes).unwrap();
+        let loaded = ScopeIndex::from_bytes(&idx.to_bytes().unwrap()).unwrap();
+
+        for idx in [&idx, &loaded] {
+            let by_name = |name| {
+                idx.scopes_by_name(name)
+                    .map(|s| (s.range, s.depth))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(by_name("render"), [(0..100, 0), (30..60, 1), (70..80, 1)]);
+            assert_eq!(by_name("update"), [(10..20, 1), (35..40, 2)]);
+
+            let by_prefix = |prefix| {
+                idx.scopes_by_name_prefix(prefix)
+                    .map(|s| s.id)
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(by_prefix(""), [0, 1, 2, 3, 5]);
+            assert_eq!(by_prefix("up"), [1, 3]);
+        }
+    }
+
+    #[test]
+    fn merge() {
+        let first = ScopeIndex::with_minified_names(vec![
+            (0..10, Some(String::from("shared")), Some(String::from("a"))),
+            (2..5, None, None),
+        ])
+        .unwrap();
+        let second = ScopeIndex::new(vec![(0..10, Some(String::from("shared")))]).unwrap();
+
+        let idx = ScopeIndex::merge([(0, &first), (10, &second)]).unwrap();
+        assert_eq!(idx.names.len(), 2);
+        assert_eq!(idx.lookup(3), ScopeLookupResult::AnonymousScope);
+        assert_eq!(idx.lookup(10), ScopeLookupResult::NamedScope("shared"));
+        assert_eq!(idx.lookup(20), ScopeLookupResult::Unknown);
+        assert_eq!(idx.lookup_scope(0).unwrap().minified_name, Some("a"));
+        assert_eq!(idx.lookup_scope(10).unwrap().minified_name, None);
+
+        let err = ScopeIndex::merge([(0, &first), (5, &second)]).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ScopeIndexErrorKind::InvalidNesting { .. }
+        ));
+
+        let err = ScopeIndex::merge([(u32::MAX - 5, &first)]).unwrap_err();
+        assert_eq!(err.kind(), &ScopeIndexErrorKind::InvalidOffset);
+    }
+
+    #[test]
+    fn rebase() {
+        let mut idx = ScopeIndex::new(vec![(5..10, Some(String::from("fn")))]).unwrap();
+
+        assert!(idx.rebase(u32::MAX - 5).is_err());
+        assert_eq!(idx.lookup(7), ScopeLookupResult::NamedScope("fn"));
+
+        idx.rebase(10).unwrap();
+        assert_eq!(idx.lookup(7), ScopeLookupResult::Unknown);
+        assert_eq!(idx.lookup(17), ScopeLookupResult::NamedScope("fn"));
+        assert_eq!(idx.lookup(20), ScopeLookupResult::Unknown);
+    }
+
+    #[test]
+    fn stats() {
+        let empty = ScopeIndex::new(vec![]).unwrap();
+        let stats = empty.stats();
+        assert_eq!(stats.num_scopes, 0);
+        assert_eq!(stats.max_depth, 0);
+        assert_eq!(stats.anonymous_ratio(), 0.0);
+
+        let scopes = vec![
+            (0..30, Some(String::from("a"))),
+            (5..25, None),
+            (10..15, Some(String::from("b"))),
+        ];
+        let idx = ScopeIndex::new(scopes).unwrap();
+        let stats = idx.stats();
+        assert_eq!(stats.num_names, 2);
+        assert_eq!(stats.num_anonymous_scopes, 1);
+        assert_eq!(stats.max_depth, 2);
+        assert!(stats.heap_size > empty.heap_size());
+        assert_eq!(stats.heap_size, idx.heap_size());
+    }
+
+    #[test]
+    fn adjacent_scopes() {
+        let scopes = vec![
+            (0..10, Some(String::from("first"))),
+            (10..20, Some(String::from("second"))),
+        ];
+
+        let idx = ScopeIndex::new(scopes).unwrap();
+
+        assert_eq!(idx.lookup(9), ScopeLookupResult::NamedScope("first"));
+        assert_eq!(idx.lookup(10), ScopeLookupResult::NamedScope("second"));
+        assert_eq!(idx.lookup(20), ScopeLookupResult::Unknown);
+    }
 }
@@ -0,0 +1,184 @@
+use std::ops::Range;
+
+use indexmap::IndexSet;
+
+use crate::format::{Format, ScopeIndexFormatError, ScopeIndexFormatErrorKind};
+use crate::scope_index::{self, resolve_scope_name, ScopeEntry, ScopeTables};
+use crate::{IndexedScope, ScopeIndex, ScopeLookupResult};
+
+/// A zero-copy view of a serialized [`ScopeIndex`].
+///
+/// The view performs lookups directly on top of the serialized data, for
+/// example a memory-mapped file, without copying the names or ranges into
+/// heap-allocated structures.
+///
+/// Parsing the view validates the data once, which makes all the following
+/// lookups infallible.
+///
+/// # Examples
+///
+/// ```
+/// use js_source_scopes::{ScopeIndex, ScopeIndexView, ScopeLookupResult};
+///
+/// let scopes = vec![
+///     (5..25, Some(String::from("parent"))),
+///     (10..15, Some(String::from("child"))),
+///     (30..50, None),
+/// ];
+/// let buf = ScopeIndex::new(scopes).unwrap().to_bytes().unwrap();
+///
+/// let view = ScopeIndexView::parse(&buf).unwrap();
+/// assert_eq!(view.lookup(3), ScopeLookupResult::Unknown);
+/// assert_eq!(view.lookup(12), ScopeLookupResult::NamedScope("child"));
+/// assert_eq!(view.lookup(40), ScopeLookupResult::AnonymousScope);
+/// ```
+#[derive(Clone, Copy)]
+pub struct ScopeIndexView<'data> {
+    format: Format<'data>,
+}
+
+impl<'data> ScopeIndexView<'data> {
+    /// Parses a view of an index that was serialized using [`ScopeIndex::to_bytes`]
+    /// or [`ScopeIndex::write_to`].
+    #[tracing::instrument(level = "trace", name = "ScopeIndexView::parse", skip_all)]
+    pub fn parse(data: &'data [u8]) -> Result<Self, ScopeIndexFormatError> {
+        let format = Format::parse(data)?;
+        Ok(Self { format })
+    }
+
+    /// Looks up the scope corresponding to the given `offset`.
+    pub fn lookup(&self, offset: u32) -> ScopeLookupResult<'data> {
+        scope_index::lookup(*self, offset)
+    }
+
+    /// Looks up the innermost scope enclosing the given `offset`.
+    ///
+    /// See [`ScopeIndex::lookup_scope`].
+    pub fn lookup_scope(&self, offset: u32) -> Option<IndexedScope<'data>> {
+        scope_index::lookup_scope(*self, offset)
+    }
+
+    /// Returns the scope with the given `id`.
+    ///
+    /// See [`ScopeIndex::scope`].
+    pub fn scope(&self, id: u32) -> Option<IndexedScope<'data>> {
+        scope_index::get_scope(*self, id)
+    }
+
+    /// Looks up the full stack of scopes enclosing the given `offset`.
+    ///
+    /// See [`ScopeIndex::lookup_stack`].
+    pub fn lookup_stack(&self, offset: u32) -> impl Iterator<Item = IndexedScope<'data>> {
+        scope_index::scope_stack(*self, offset)
+    }
+
+    /// Returns all the scopes intersecting the given byte `range`.
+    ///
+    /// See [`ScopeIndex::scopes_in_range`].
+    pub fn scopes_in_range(&self, range: Range<u32>) -> impl Iterator<Item = IndexedScope<'data>> {
+        scope_index::scopes_in_range(*self, range)
+    }
+
+    /// Returns all the scopes with the given `name`.
+    ///
+    /// See [`ScopeIndex::scopes_by_name`].
+    pub fn scopes_by_name<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> impl Iterator<Item = IndexedScope<'data>> + 'a {
+        self.scopes_by_name_matching(move |n| n == name)
+    }
+
+    /// Returns all the scopes with a name starting with `prefix`.
+    ///
+    /// See [`ScopeIndex::scopes_by_name_prefix`].
+    pub fn scopes_by_name_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = IndexedScope<'data>> + 'a {
+        self.scopes_by_name_matching(move |n| n.starts_with(prefix))
+    }
+
+    fn scopes_by_name_matching<'a>(
+        &'a self,
+        matches: impl Fn(&str) -> bool + 'a,
+    ) -> impl Iterator<Item = IndexedScope<'data>> + 'a {
+        // the names table has no hash index, so match every name once up front
+        let matching: Vec<bool> = (0..self.format.num_names())
+            .map(|idx| matches(self.format.name(idx)))
+            .collect();
+        scope_index::scopes_matching(*self, move |scope| {
+            matching
+                .get(scope.name_idx as usize)
+                .copied()
+                .unwrap_or(false)
+        })
+    }
+
+    /// Returns an iterator over the scopes in this view and their starting
+    /// offsets.
+    ///
+    /// Scopes are returned in order of their starting offsets.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, ScopeLookupResult<'data>)> + '_ {
+        (0..self.format.num_ranges()).map(|idx| {
+            let (offset, scope_idx) = self.format.range(idx);
+            (offset, resolve_scope_name(*self, scope_idx))
+        })
+    }
+
+    /// Copies the data of this view into an owned [`ScopeIndex`].
+    pub fn to_index(&self) -> Result<ScopeIndex, ScopeIndexFormatError> {
+        let mut names = IndexSet::with_capacity(self.format.num_names());
+        for idx in 0..self.format.num_names() {
+            if !names.insert(self.format.name(idx).to_owned()) {
+                // duplicated names would shift the indices of all following names
+                return Err(ScopeIndexFormatErrorKind::InvalidName.into());
+            }
+        }
+        let scopes = (0..self.format.num_scopes() as u32)
+            .map(|idx| self.format.scope(idx))
+            .collect();
+        let ranges = (0..self.format.num_ranges())
+            .map(|idx| self.format.range(idx))
+            .collect();
+
+        Ok(ScopeIndex {
+            names,
+            scopes,
+            ranges,
+            scopes_by_name: Default::default(),
+        })
+    }
+}
+
+impl<'data> ScopeTables<'data> for ScopeIndexView<'data> {
+    fn num_ranges(&self) -> usize {
+        self.format.num_ranges()
+    }
+
+    fn num_scopes(&self) -> usize {
+        self.format.num_scopes()
+    }
+
+    fn range(&self, idx: usize) -> (u32, u32) {
+        self.format.range(idx)
+    }
+
+    fn scope(&self, scope_idx: u32) -> ScopeEntry {
+        self.format.scope(scope_idx)
+    }
+
+    fn name(&self, name_idx: u32) -> Option<&'data str> {
+        ((name_idx as usize) < self.format.num_names()).then(|| self.format.name(name_idx as usize))
+    }
+}
+
+impl std::fmt::Debug for ScopeIndexView<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScopeIndexView")
+            .field("num_ranges", &self.format.num_ranges())
+            .field("num_scopes", &self.format.num_scopes())
+            .field("num_names", &self.format.num_names())
+            .finish()
+    }
+}
@@ -8,7 +8,42 @@ use swc_ecma_visit::swc_ecma_ast as ast;
 use crate::swc::convert_span;
 
 /// An abstract scope name which can consist of multiple [`NameComponent`]s.
-#[derive(Debug)]
+///
+/// Scope names are usually produced by [`extract_scope_names`](crate::extract_scope_names),
+/// but can also be constructed by hand from a list of components, or from a
+/// plain string.
+///
+/// # Examples
+///
+/// ```
+/// use js_source_scopes::{NameComponent, ScopeName};
+///
+/// let name = ScopeName::from_components([
+///     NameComponent::source_identifier("obj", 6..9),
+///     NameComponent::interpolation("."),
+///     NameComponent::source_identifier("method", 20..26),
+/// ]);
+/// assert_eq!(name.to_string(), "obj.method");
+///
+/// let plain = ScopeName::from("obj.method");
+/// assert_eq!(plain.to_string(), name.to_string());
+/// assert_ne!(plain, name);
+/// ```
+///
+/// # Serialization
+///
+/// With the `serde` feature enabled, a scope name is serialized as a list of
+/// its [`NameComponent`]s:
+///
+/// ```json
+/// [{"text": "obj", "range": {"start": 6, "end": 9}}, {"text": "."}]
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
 pub struct ScopeName {
     pub(crate) components: VecDeque<NameComponent>,
 }
@@ -20,12 +55,39 @@ impl ScopeName {
         }
     }
 
+    /// Creates a new scope name out of the given [`NameComponent`]s.
+    pub fn from_components(components: impl IntoIterator<Item = NameComponent>) -> Self {
+        Self {
+            components: components.into_iter().collect(),
+        }
+    }
+
     /// An Iterator over the individual components of this scope name.
     pub fn components(&self) -> impl Iterator<Item = &NameComponent> + '_ {
         self.components.iter()
     }
 }
 
+impl FromIterator<NameComponent> for ScopeName {
+    fn from_iter<I: IntoIterator<Item = NameComponent>>(iter: I) -> Self {
+        Self::from_components(iter)
+    }
+}
+
+impl From<&str> for ScopeName {
+    /// Creates a scope name consisting of a single synthetic component.
+    fn from(s: &str) -> Self {
+        Self::from(String::from(s))
+    }
+}
+
+impl From<String> for ScopeName {
+    /// Creates a scope name consisting of a single synthetic component.
+    fn from(s: String) -> Self {
+        Self::from_components([NameComponent::interpolation(s)])
+    }
+}
+
 impl Display for ScopeName {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for c in self.components() {
@@ -36,17 +98,52 @@ impl Display for ScopeName {
 }
 
 /// An individual component of a [`ScopeName`].
-#[derive(Debug)]
+///
+/// # Serialization
+///
+/// With the `serde` feature enabled, a component is serialized as an object
+/// with its `text`, and the `range` of the corresponding source token.
+/// Synthetic components have no `range`:
+///
+/// ```json
+/// {"text": "obj", "range": {"start": 6, "end": 9}}
+/// {"text": "."}
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "NameComponentRepr", into = "NameComponentRepr")
+)]
 pub struct NameComponent {
     pub(crate) inner: NameComponentInner,
 }
 
 impl NameComponent {
+    /// Creates a synthetic component that does not correspond to a specific
+    /// token inside the source text, like a `.` separator.
+    pub fn interpolation(text: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            inner: NameComponentInner::Interpolation(text.into()),
+        }
+    }
+
+    /// Creates a component corresponding to the identifier token `text`
+    /// located at `range` inside the source text.
+    pub fn source_identifier(text: impl Into<String>, range: Range<u32>) -> Self {
+        Self {
+            inner: NameComponentInner::SourceIdentifierToken {
+                text: text.into(),
+                range,
+            },
+        }
+    }
+
     /// The source text of this component.
     pub fn text(&self) -> &str {
         match &self.inner {
             NameComponentInner::Interpolation(s) => s,
-            NameComponentInner::SourceIdentifierToken(t) => &t.sym,
+            NameComponentInner::SourceIdentifierToken { text, .. } => text,
         }
     }
 
@@ -56,25 +153,56 @@ impl NameComponent {
     /// to a specific token inside the source text.
     pub fn range(&self) -> Option<Range<u32>> {
         match &self.inner {
-            NameComponentInner::SourceIdentifierToken(t) => Some(convert_span(t.span)),
+            NameComponentInner::SourceIdentifierToken { range, .. } => Some(range.clone()),
             _ => None,
         }
     }
 
     pub(crate) fn interp(s: impl Into<Cow<'static, str>>) -> Self {
-        Self {
-            inner: NameComponentInner::Interpolation(s.into()),
-        }
+        Self::interpolation(s)
     }
     pub(crate) fn ident(ident: ast::Ident) -> Self {
-        Self {
-            inner: NameComponentInner::SourceIdentifierToken(ident),
-        }
+        Self::source_identifier(ident.sym.to_string(), convert_span(ident.span))
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub(crate) enum NameComponentInner {
     Interpolation(Cow<'static, str>),
-    SourceIdentifierToken(ast::Ident),
+    SourceIdentifierToken { text: String, range: Range<u32> },
+}
+
+/// The serialized representation of a [`NameComponent`].
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct NameComponentRepr {
+    text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    range: Option<Range<u32>>,
+}
+
+#[cfg(feature = "serde")]
+impl From<NameComponent> for NameComponentRepr {
+    fn from(component: NameComponent) -> Self {
+        match component.inner {
+            NameComponentInner::Interpolation(text) => Self {
+                text: text.into_owned(),
+                range: None,
+            },
+            NameComponentInner::SourceIdentifierToken { text, range } => Self {
+                text,
+                range: Some(range),
+            },
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<NameComponentRepr> for NameComponent {
+    fn from(repr: NameComponentRepr) -> Self {
+        match repr.range {
+            Some(range) => Self::source_identifier(repr.text, range),
+            None => Self::interpolation(repr.text),
+        }
+    }
 }
@@ -1,3 +1,6 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
 /// A structure allowing fast line/column <-> byte offset remapping.
 ///
 /// The primary use-case is to allow efficient conversion between
@@ -6,6 +9,14 @@
 /// [`SourceContext`] for a source that exceeds the range of a `u32` will result
 /// in an `Err`.
 ///
+/// Columns are given in UTF-16 code units by default, which matches browsers
+/// and source maps. A different [`ColumnUnit`] can be chosen using
+/// [`SourceContext::with_options`], or per conversion.
+///
+/// Sources which are not valid UTF-8 can be used with
+/// [`SourceContext::from_bytes`], in which case all offsets refer to the raw
+/// bytes of the source.
+///
 /// # Examples
 ///
 /// ```
@@ -23,153 +34,769 @@
 /// ```
 pub struct SourceContext<T> {
     src: T,
-    index: Vec<Mapping>,
+    /// Accesses the raw bytes of `src`.
+    as_bytes: fn(&T) -> &[u8],
+    /// All the lines of the source.
+    lines: Vec<Line>,
+    /// Checkpoints inside of lines that are not pure ASCII.
+    checkpoints: Vec<Checkpoint>,
+    options: SourceContextOptions,
 }
 
-/// When creating the [`SourceContext`], create a mapping every [`CHUNKS`] char.
+/// When creating the [`SourceContext`], create a checkpoint every [`CHUNKS`]
+/// bytes within lines that contain non-ASCII characters.
 ///
-/// For example for a 80kiB byte file, we would have 640 of these mappings,
-/// weighing about 7k in memory.
+/// For example for a 80kiB byte file, we would have at most 640 of these
+/// checkpoints, weighing about 7k in memory. Lines that are pure ASCII do not
+/// need any checkpoints, as their columns correspond to byte offsets.
 const CHUNKS: usize = 128;
 
-/// A mapping in the [`SourceContext`] index.
+/// A char boundary inside of a line of a [`SourceContext`], used to resume
+/// conversions from.
 #[derive(Clone, Copy)]
-struct Mapping {
-    /// The current byte offset.
+struct Cursor {
+    /// The 0-indexed line.
+    line: usize,
+    /// The byte offset.
     offset: u32,
-    /// Current 0-indexed line.
-    line: u32,
-    /// Current 0-indexed UTF-16 column.
+    /// The 0-indexed column, in the unit of the conversion.
     column: u32,
 }
 
+impl Cursor {
+    fn position(&self) -> SourcePosition {
+        SourcePosition::new(self.line as u32, self.column)
+    }
+}
+
+/// A line in the [`SourceContext`] index.
+#[derive(Clone, Copy)]
+struct Line {
+    /// The byte offset at which the line starts.
+    start: u32,
+    /// The index of the first [`Checkpoint`] of this line.
+    ///
+    /// The checkpoints of a line extend up to the first checkpoint of the next
+    /// line. Lines that are pure ASCII do not have any checkpoints, all other
+    /// lines have a checkpoint at their start.
+    first_checkpoint: u32,
+}
+
+/// A checkpoint inside of a [`Line`] in the [`SourceContext`] index.
+#[derive(Clone, Copy)]
+struct Checkpoint {
+    /// The byte offset of the checkpoint.
+    offset: u32,
+    /// The 0-indexed UTF-16 column of the checkpoint.
+    utf16_column: u32,
+    /// The 0-indexed unicode scalar column of the checkpoint.
+    scalar_column: u32,
+}
+
+impl Checkpoint {
+    /// The column of this checkpoint in the given `unit`.
+    fn column(&self, line_start: u32, unit: ColumnUnit) -> u32 {
+        match unit {
+            ColumnUnit::Utf16 => self.utf16_column,
+            ColumnUnit::Utf8 => self.offset - line_start,
+            ColumnUnit::UnicodeScalar => self.scalar_column,
+        }
+    }
+}
+
 impl<T: AsRef<str>> SourceContext<T> {
+    /// Construct a new Source Context from the given `src` buffer.
+    #[tracing::instrument(level = "trace", name = "SourceContext::new", skip_all)]
+    pub fn new(src: T) -> Result<Self, SourceContextError> {
+        Self::with_options(src, SourceContextOptions::default())
+    }
+
+    /// Construct a new Source Context from the given `src` buffer, using the
+    /// given `options`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::{ColumnUnit, SourceContext, SourceContextOptions, SourcePosition};
+    ///
+    /// let options = SourceContextOptions {
+    ///     column_unit: ColumnUnit::Utf8,
+    ///     ..Default::default()
+    /// };
+    /// let ctx = SourceContext::with_options("let ö = 1;", options).unwrap();
+    ///
+    /// assert_eq!(ctx.offset_to_position(7), Some(SourcePosition::new(0, 7)));
+    /// assert_eq!(
+    ///     ctx.offset_to_position_in(7, ColumnUnit::Utf16),
+    ///     Some(SourcePosition::new(0, 6))
+    /// );
+    /// ```
+    #[tracing::instrument(level = "trace", name = "SourceContext::with_options", skip_all)]
+    pub fn with_options(src: T, options: SourceContextOptions) -> Result<Self, SourceContextError> {
+        fn as_bytes<T: AsRef<str>>(src: &T) -> &[u8] {
+            src.as_ref().as_bytes()
+        }
+        // the source was already decoded into a `str`
+        let options = SourceContextOptions {
+            encoding: SourceEncoding::Utf8,
+            ..options
+        };
+        SourceContext::build(src, as_bytes::<T>, options)
+    }
+}
+
+impl<T: AsRef<[u8]>> SourceContext<T> {
+    /// Construct a new Source Context from the given `src` byte buffer, which
+    /// might not be valid UTF-8.
+    ///
+    /// Invalid UTF-8 sequences are treated as a single U+FFFD replacement
+    /// character each, like [`String::from_utf8_lossy`] does. All offsets refer
+    /// to the raw bytes of `src`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::{SourceContext, SourcePosition};
+    ///
+    /// let src = b"let \xFF = 1;\nfoo();";
+    /// let ctx = SourceContext::from_bytes(&src[..]).unwrap();
+    ///
+    /// assert_eq!(ctx.offset_to_position(7), Some(SourcePosition::new(0, 7)));
+    /// assert_eq!(ctx.position_to_offset(SourcePosition::new(1, 0)), Some(11));
+    /// assert_eq!(ctx.line(0).as_deref(), Some("let \u{FFFD} = 1;"));
+    /// ```
+    #[tracing::instrument(level = "trace", name = "SourceContext::from_bytes", skip_all)]
+    pub fn from_bytes(src: T) -> Result<Self, SourceContextError> {
+        Self::from_bytes_with_options(src, SourceContextOptions::default())
+    }
+
+    /// Construct a new Source Context from the given `src` byte buffer, using
+    /// the given `options`.
+    ///
+    /// The [`SourceEncoding`] of the `options` determines how the bytes are
+    /// decoded into characters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::{SourceContext, SourceContextOptions, SourceEncoding, SourcePosition};
+    ///
+    /// // "é" encoded as Latin-1
+    /// let src = b"let caf\xE9 = 1;";
+    /// let options = SourceContextOptions {
+    ///     encoding: SourceEncoding::Latin1,
+    ///     ..Default::default()
+    /// };
+    /// let ctx = SourceContext::from_bytes_with_options(&src[..], options).unwrap();
+    ///
+    /// assert_eq!(ctx.offset_to_position(9), Some(SourcePosition::new(0, 9)));
+    /// assert_eq!(ctx.line(0).as_deref(), Some("let café = 1;"));
+    /// ```
+    #[tracing::instrument(
+        level = "trace",
+        name = "SourceContext::from_bytes_with_options",
+        skip_all
+    )]
+    pub fn from_bytes_with_options(
+        src: T,
+        options: SourceContextOptions,
+    ) -> Result<Self, SourceContextError> {
+        fn as_bytes<T: AsRef<[u8]>>(src: &T) -> &[u8] {
+            src.as_ref()
+        }
+        SourceContext::build(src, as_bytes::<T>, options)
+    }
+}
+
+impl<T> SourceContext<T> {
     /// Unwrap this Source Context into the inner source buffer.
     pub fn into_inner(self) -> T {
         self.src
     }
 
-    /// Construct a new Source Context from the given `src` buffer.
-    #[tracing::instrument(level = "trace", name = "SourceContext::new", skip_all)]
-    pub fn new(src: T) -> Result<Self, SourceContextError> {
-        let buf = src.as_ref();
+    fn build(
+        src: T,
+        as_bytes: fn(&T) -> &[u8],
+        options: SourceContextOptions,
+    ) -> Result<Self, SourceContextError> {
+        let buf = as_bytes(&src);
         // we can do the bounds check once in the beginning, that guarantees that
         // all the other offsets are within `u32` bounds.
         let _len: u32 = buf.len().try_into().map_err(|_| SourceContextError(()))?;
 
-        let mut index = vec![];
+        let mut lines = vec![];
+        let mut checkpoints = vec![];
 
-        let mut offset = 0;
-        let mut line = 0;
-        let mut column = 0;
-        for (i, c) in buf.chars().enumerate() {
-            if i % CHUNKS == 0 {
-                index.push(Mapping {
-                    offset: offset as u32,
-                    line,
-                    column: column as u32,
-                });
+        let mut start = 0;
+        loop {
+            let line_break = find_line_break(buf, start, options);
+            let end = line_break.map_or(buf.len(), |(end, _)| end);
+
+            lines.push(Line {
+                start: start as u32,
+                first_checkpoint: checkpoints.len() as u32,
+            });
+
+            let line = &buf[start..end];
+            if !line.is_ascii() {
+                let mut utf16_column = 0;
+                let mut next_checkpoint = 0;
+                for (scalar_column, (i, _, c)) in decode(line, options.encoding).enumerate() {
+                    if i >= next_checkpoint {
+                        checkpoints.push(Checkpoint {
+                            offset: (start + i) as u32,
+                            utf16_column,
+                            scalar_column: scalar_column as u32,
+                        });
+                        next_checkpoint = i + CHUNKS;
+                    }
+                    utf16_column += c.len_utf16() as u32;
+                }
             }
-            offset += c.len_utf8();
-            if c == '\n' {
-                line += 1;
-                column = 0;
-            } else {
-                column += c.len_utf16();
+
+            match line_break {
+                Some((end, len)) => start = end + len,
+                None => break,
             }
         }
 
-        Ok(Self { src, index })
+        Ok(Self {
+            src,
+            as_bytes,
+            lines,
+            checkpoints,
+            options,
+        })
     }
 
-    /// Converts a byte offset into the source to the corresponding line/column.
+    /// Returns the heap memory used by the index of this Source Context, in bytes.
     ///
-    /// The column is given in UTF-16 code points.
-    pub fn offset_to_position(&self, offset: u32) -> Option<SourcePosition> {
-        let mapping = match self
-            .index
-            .binary_search_by_key(&offset, |mapping| mapping.offset)
+    /// This does not include the memory of the source buffer itself.
+    pub fn heap_size(&self) -> usize {
+        self.lines.capacity() * std::mem::size_of::<Line>()
+            + self.checkpoints.capacity() * std::mem::size_of::<Checkpoint>()
+    }
+
+    /// The raw bytes of the source.
+    fn bytes(&self) -> &[u8] {
+        (self.as_bytes)(&self.src)
+    }
+
+    /// The unit in which columns are given, unless specified otherwise.
+    pub fn column_unit(&self) -> ColumnUnit {
+        self.options.column_unit
+    }
+
+    /// The total number of lines in the source.
+    ///
+    /// Lines are separated by the configured [`LineTerminators`], so a source
+    /// ending with a newline has a trailing empty line, and an empty source
+    /// consists of one empty line.
+    pub fn line_count(&self) -> u32 {
+        self.lines.len() as u32
+    }
+
+    /// Returns the byte range of the given 0-based `line`.
+    ///
+    /// The range does not include the line terminator. A `\r\n` sequence is
+    /// treated as a single line terminator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::SourceContext;
+    ///
+    /// let ctx = SourceContext::new("first\r\nsecond\n").unwrap();
+    /// assert_eq!(ctx.line_count(), 3);
+    /// assert_eq!(ctx.line_range(0), Some(0..5));
+    /// assert_eq!(ctx.line_range(1), Some(7..13));
+    /// assert_eq!(ctx.line_range(2), Some(14..14));
+    /// assert_eq!(ctx.line_range(3), None);
+    /// ```
+    pub fn line_range(&self, line: u32) -> Option<Range<u32>> {
+        let bytes = self.bytes();
+        let start = self.lines.get(line as usize)?.start;
+        let mut end = self.line_end(line as usize);
+        if end > start
+            && bytes.get(end as usize) == Some(&b'\n')
+            && bytes[end as usize - 1] == b'\r'
         {
-            Ok(idx) => self.index[idx],
-            Err(0) => Mapping {
-                offset: 0,
-                line: 0,
-                column: 0,
+            end -= 1;
+        }
+        Some(start..end)
+    }
+
+    /// Returns the text of the given 0-based `line`, without its line terminator.
+    ///
+    /// The text is borrowed from the source, unless the source is not valid
+    /// UTF-8 and has to be decoded according to its [`SourceEncoding`].
+    ///
+    /// See [`SourceContext::line_range`].
+    pub fn line(&self, line: u32) -> Option<Cow<'_, str>> {
+        let range = self.line_range(line)?;
+        let bytes = &self.bytes()[range.start as usize..range.end as usize];
+        Some(match self.options.encoding {
+            SourceEncoding::Utf8 => String::from_utf8_lossy(bytes),
+            SourceEncoding::Latin1 => match std::str::from_utf8(bytes) {
+                Ok(text) if bytes.is_ascii() => Cow::Borrowed(text),
+                _ => Cow::Owned(bytes.iter().map(|b| *b as char).collect()),
             },
-            Err(idx) => self.index[idx - 1],
-        };
+        })
+    }
 
-        let mut byte_offset = mapping.offset as usize;
-        let mut line = mapping.line;
-        let mut column = mapping.column as usize;
+    /// Returns the line of the given `position`, together with up to
+    /// `context` lines before and after it.
+    ///
+    /// Returns `None` if the line of the `position` is outside of the source.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::{SourceContext, SourcePosition};
+    ///
+    /// let ctx = SourceContext::new("a\nb\nc\nd").unwrap();
+    ///
+    /// let lines = ctx.context_lines(SourcePosition::new(1, 0), 2).unwrap();
+    /// assert_eq!(lines.pre_context, ["a"]);
+    /// assert_eq!(lines.context_line, "b");
+    /// assert_eq!(lines.post_context, ["c", "d"]);
+    /// ```
+    pub fn context_lines(
+        &self,
+        position: SourcePosition,
+        context: u32,
+    ) -> Option<ContextLines<'_>> {
+        let line = position.line;
+        let context_line = self.line(line)?;
 
-        for c in self.src.as_ref().get(byte_offset..)?.chars() {
-            if byte_offset >= offset as usize {
-                return Some(SourcePosition::new(line, column as u32));
-            }
+        let first_line = line.saturating_sub(context);
+        let last_line = line.saturating_add(context).min(self.line_count() - 1);
+        let lines = |range: Range<u32>| range.filter_map(|line| self.line(line)).collect();
 
-            byte_offset += c.len_utf8();
-            if c == '\n' {
-                line += 1;
-                column = 0;
-            } else {
-                column += c.len_utf16();
-            }
-        }
+        Some(ContextLines {
+            pre_context: lines(first_line..line),
+            context_line,
+            post_context: lines(line + 1..last_line + 1),
+        })
+    }
 
-        None
+    /// Converts a byte offset into the source to the corresponding line/column.
+    ///
+    /// The column is given in the [`ColumnUnit`] of this Source Context.
+    pub fn offset_to_position(&self, offset: u32) -> Option<SourcePosition> {
+        self.offset_to_position_in(offset, self.options.column_unit)
+    }
+
+    /// Converts a byte offset into the source to the corresponding line/column,
+    /// with the column given in the given `unit`.
+    pub fn offset_to_position_in(&self, offset: u32, unit: ColumnUnit) -> Option<SourcePosition> {
+        Some(self.locate_offset(offset, unit, None)?.position())
     }
 
     /// Converts the given line/column to the corresponding byte offset inside the source.
+    ///
+    /// The column is expected in the [`ColumnUnit`] of this Source Context.
     pub fn position_to_offset(&self, position: SourcePosition) -> Option<u32> {
-        let SourcePosition { line, column } = position;
-        let mapping = match self
-            .index
-            .binary_search_by_key(&(line, column), |mapping| (mapping.line, mapping.column))
-        {
-            Ok(idx) => self.index[idx],
-            Err(0) => Mapping {
-                offset: 0,
-                line: 0,
+        self.position_to_offset_in(position, self.options.column_unit)
+    }
+
+    /// Converts the given line/column, with the column given in the given `unit`,
+    /// to the corresponding byte offset inside the source.
+    pub fn position_to_offset_in(&self, position: SourcePosition, unit: ColumnUnit) -> Option<u32> {
+        Some(self.locate_position(position, unit, None)?.offset)
+    }
+
+    /// Converts the given 1-based [`FramePosition`] to the corresponding byte
+    /// offset inside the source.
+    ///
+    /// The column of a [`FramePosition`] is always given in UTF-16 code units.
+    pub fn frame_position_to_offset(&self, position: FramePosition) -> Option<u32> {
+        self.position_to_offset_in(position.to_source_position()?, ColumnUnit::Utf16)
+    }
+
+    /// Converts a byte offset into the source to the corresponding 1-based
+    /// [`FramePosition`].
+    pub fn offset_to_frame_position(&self, offset: u32) -> Option<FramePosition> {
+        let position = self.offset_to_position_in(offset, ColumnUnit::Utf16)?;
+        position.to_frame_position()
+    }
+
+    /// Converts multiple byte offsets into the source to the corresponding
+    /// line/columns at once.
+    ///
+    /// This is equivalent to calling [`SourceContext::offset_to_position`] for
+    /// each of the `offsets`, but processes them in sorted order: each line is
+    /// only searched for among the lines following the previous result, and
+    /// scanning a line resumes from the previous result if it is on the same
+    /// line. The `offsets` do not need to be sorted, and the results are
+    /// returned in the same order as the `offsets`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::{SourceContext, SourcePosition};
+    ///
+    /// let ctx = SourceContext::new("const a = 1;\nconst b = 2;").unwrap();
+    ///
+    /// let positions = ctx.offsets_to_positions(&[19, 6, 100]);
+    /// assert_eq!(
+    ///     positions,
+    ///     [
+    ///         Some(SourcePosition::new(1, 6)),
+    ///         Some(SourcePosition::new(0, 6)),
+    ///         None
+    ///     ]
+    /// );
+    /// ```
+    pub fn offsets_to_positions(&self, offsets: &[u32]) -> Vec<Option<SourcePosition>> {
+        self.offsets_to_positions_in(offsets, self.options.column_unit)
+    }
+
+    /// Converts multiple byte offsets into the source to the corresponding
+    /// line/columns at once, with the columns given in the given `unit`.
+    ///
+    /// See [`SourceContext::offsets_to_positions`].
+    pub fn offsets_to_positions_in(
+        &self,
+        offsets: &[u32],
+        unit: ColumnUnit,
+    ) -> Vec<Option<SourcePosition>> {
+        let mut order: Vec<_> = (0..offsets.len()).collect();
+        order.sort_by_key(|idx| offsets[*idx]);
+
+        let mut results = vec![None; offsets.len()];
+        let mut cursor = None;
+        for idx in order {
+            if let Some(found) = self.locate_offset(offsets[idx], unit, cursor) {
+                results[idx] = Some(found.position());
+                cursor = Some(found);
+            }
+        }
+        results
+    }
+
+    /// Converts multiple line/columns to the corresponding byte offsets inside
+    /// the source at once.
+    ///
+    /// This is equivalent to calling [`SourceContext::position_to_offset`] for
+    /// each of the `positions`, but processes them in sorted order, so that
+    /// scanning a line resumes from the previous result if it is on the same
+    /// line. The `positions` do not need to be sorted, and the results are
+    /// returned in the same order as the `positions`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::{SourceContext, SourcePosition};
+    ///
+    /// let ctx = SourceContext::new("const a = 1;\nconst b = 2;").unwrap();
+    ///
+    /// let positions = [SourcePosition::new(1, 6), SourcePosition::new(0, 6)];
+    /// assert_eq!(ctx.positions_to_offsets(&positions), [Some(19), Some(6)]);
+    /// ```
+    pub fn positions_to_offsets(&self, positions: &[SourcePosition]) -> Vec<Option<u32>> {
+        self.positions_to_offsets_in(positions, self.options.column_unit)
+    }
+
+    /// Converts multiple line/columns, with the columns given in the given
+    /// `unit`, to the corresponding byte offsets inside the source at once.
+    ///
+    /// See [`SourceContext::positions_to_offsets`].
+    pub fn positions_to_offsets_in(
+        &self,
+        positions: &[SourcePosition],
+        unit: ColumnUnit,
+    ) -> Vec<Option<u32>> {
+        let mut order: Vec<_> = (0..positions.len()).collect();
+        order.sort_by_key(|idx| positions[*idx]);
+
+        let mut results = vec![None; positions.len()];
+        let mut cursor = None;
+        for idx in order {
+            if let Some(found) = self.locate_position(positions[idx], unit, cursor) {
+                results[idx] = Some(found.offset);
+                cursor = Some(found);
+            }
+        }
+        results
+    }
+
+    /// Finds the char boundary corresponding to the byte `offset`.
+    ///
+    /// The search resumes from the given `cursor` if it is on the same line
+    /// and before `offset`, and the columns of the `cursor` are in `unit`.
+    fn locate_offset(
+        &self,
+        offset: u32,
+        unit: ColumnUnit,
+        cursor: Option<Cursor>,
+    ) -> Option<Cursor> {
+        let src = self.bytes();
+        if offset as usize >= src.len() {
+            return None;
+        }
+
+        // the first line always starts at `0`
+        let line = match cursor {
+            Some(cursor) if cursor.offset <= offset => {
+                let lines = &self.lines[cursor.line..];
+                cursor.line + lines.partition_point(|line| line.start <= offset) - 1
+            }
+            _ => self.lines.partition_point(|line| line.start <= offset) - 1,
+        };
+        let line_start = self.lines[line].start;
+        let line_end = self.line_end(line);
+        if offset > line_end {
+            // the offset points into a multi-byte line terminator, which is out
+            // of bounds if it is the last char of the source
+            let next_start = self.lines[line + 1].start;
+            return ((next_start as usize) < src.len()).then_some(Cursor {
+                line: line + 1,
+                offset: next_start,
                 column: 0,
-            },
-            Err(idx) => self.index[idx - 1],
+            });
+        }
+
+        let checkpoints = self.line_checkpoints(line);
+        if checkpoints.is_empty() {
+            // all columns correspond to bytes in pure ASCII lines
+            return Some(Cursor {
+                line,
+                offset,
+                column: offset - line_start,
+            });
+        }
+
+        let idx = checkpoints.partition_point(|checkpoint| checkpoint.offset <= offset);
+        let checkpoint = checkpoints[idx - 1];
+        let mut start = Cursor {
+            line,
+            offset: checkpoint.offset,
+            column: checkpoint.column(line_start, unit),
         };
+        if let Some(cursor) = cursor {
+            if cursor.line == line && cursor.offset > start.offset && cursor.offset <= offset {
+                start = cursor;
+            }
+        }
 
-        let mut byte_offset = mapping.offset as usize;
-        let mut mapping_line = mapping.line;
-        let mut mapping_column = mapping.column as usize;
+        let text = &src[start.offset as usize..line_end as usize];
+        let mut found = Cursor {
+            offset: line_end,
+            ..start
+        };
+        for (i, len, c) in decode(text, self.options.encoding) {
+            if start.offset + i as u32 >= offset {
+                found.offset = start.offset + i as u32;
+                break;
+            }
+            found.column += unit.len(c, len) as u32;
+        }
+        // the offset points into the middle of the last char of the source
+        ((found.offset as usize) < src.len()).then_some(found)
+    }
+
+    /// Finds the char boundary corresponding to the given `position`, with its
+    /// column given in `unit`.
+    ///
+    /// The search resumes from the given `cursor` if it is on the same line
+    /// and before `position`, and the columns of the `cursor` are in `unit`.
+    fn locate_position(
+        &self,
+        position: SourcePosition,
+        unit: ColumnUnit,
+        cursor: Option<Cursor>,
+    ) -> Option<Cursor> {
+        let SourcePosition { line, column } = position;
+        let src = self.bytes();
+        let line = line as usize;
+        let line_start = self.lines.get(line)?.start;
+        let line_end = self.line_end(line);
 
-        for c in self.src.as_ref().get(byte_offset..)?.chars() {
-            if mapping_line == line && mapping_column >= column as usize {
-                return Some(byte_offset as u32);
+        let checkpoints = self.line_checkpoints(line);
+        let (offset, column) = if checkpoints.is_empty() {
+            // all columns correspond to bytes in pure ASCII lines
+            (line_start.checked_add(column)?, column)
+        } else {
+            let idx = checkpoints
+                .partition_point(|checkpoint| checkpoint.column(line_start, unit) <= column);
+            let checkpoint = checkpoints[idx - 1];
+            let mut start = Cursor {
+                line,
+                offset: checkpoint.offset,
+                column: checkpoint.column(line_start, unit),
+            };
+            if let Some(cursor) = cursor {
+                if cursor.line == line && cursor.offset > start.offset && cursor.column <= column {
+                    start = cursor;
+                }
             }
 
-            byte_offset += c.len_utf8();
-            if c == '\n' {
-                mapping_line += 1;
-                mapping_column = 0;
-                // the column we were looking for is out of bounds
-                if mapping_line > line {
-                    return None;
+            let text = &src[start.offset as usize..line_end as usize];
+            let mut current_column = start.column;
+            let mut offset = line_end;
+            for (i, len, c) in decode(text, self.options.encoding) {
+                if current_column >= column {
+                    offset = start.offset + i as u32;
+                    break;
                 }
-            } else {
-                mapping_column += c.len_utf16();
+                current_column += unit.len(c, len) as u32;
+            }
+            if current_column < column {
+                return None;
             }
+            // the column might point into the middle of a char
+            (offset, current_column)
+        };
+
+        // the column we were looking for is out of bounds
+        (offset <= line_end && (offset as usize) < src.len()).then_some(Cursor {
+            line,
+            offset,
+            column,
+        })
+    }
+
+    /// Converts a `column` on the given 0-based `line` from one unit to another.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::{ColumnUnit, SourceContext};
+    ///
+    /// let ctx = SourceContext::new("let 🚀 = 1;").unwrap();
+    /// // the rocket takes 4 bytes, 2 UTF-16 code units and 1 unicode scalar
+    /// assert_eq!(ctx.convert_column(0, 8, ColumnUnit::Utf8, ColumnUnit::Utf16), Some(6));
+    /// assert_eq!(ctx.convert_column(0, 6, ColumnUnit::Utf16, ColumnUnit::UnicodeScalar), Some(5));
+    /// ```
+    pub fn convert_column(
+        &self,
+        line: u32,
+        column: u32,
+        from: ColumnUnit,
+        to: ColumnUnit,
+    ) -> Option<u32> {
+        let offset = self.position_to_offset_in(SourcePosition::new(line, column), from)?;
+        Some(self.offset_to_position_in(offset, to)?.column)
+    }
+
+    /// Returns the byte offset of the line terminator of the given `line`, or
+    /// the end of the source for the last line.
+    fn line_end(&self, line: usize) -> u32 {
+        let bytes = self.bytes();
+        match self.lines.get(line + 1) {
+            Some(next_line) => {
+                let terminator_len = match bytes[next_line.start as usize - 1] {
+                    b'\n' | b'\r' => 1,
+                    // the last byte of `\u{2028}` or `\u{2029}`
+                    _ => 3,
+                };
+                next_line.start - terminator_len
+            }
+            None => bytes.len() as u32,
         }
+    }
 
-        None
+    /// Returns all the [`Checkpoint`]s of the given `line`.
+    fn line_checkpoints(&self, line: usize) -> &[Checkpoint] {
+        let start = self.lines[line].first_checkpoint as usize;
+        let end = self
+            .lines
+            .get(line + 1)
+            .map_or(self.checkpoints.len(), |line| {
+                line.first_checkpoint as usize
+            });
+        &self.checkpoints[start..end]
+    }
+}
+
+/// Finds the next line terminator in `bytes`, starting at `offset`.
+///
+/// Returns the byte offset and byte length of the line terminator.
+fn find_line_break(
+    bytes: &[u8],
+    mut offset: usize,
+    options: SourceContextOptions,
+) -> Option<(usize, usize)> {
+    // `\u{2028}` and `\u{2029}` can not be represented in Latin-1
+    let lead_byte = match options.encoding {
+        SourceEncoding::Utf8 => 0xE2,
+        SourceEncoding::Latin1 => b'\n',
+    };
+    match options.line_terminators {
+        LineTerminators::Newline => {
+            let len = bytes[offset..].iter().position(|b| *b == b'\n')?;
+            Some((offset + len, 1))
+        }
+        LineTerminators::Ecmascript => loop {
+            let len = bytes[offset..]
+                .iter()
+                .position(|b| matches!(*b, b'\n' | b'\r') || *b == lead_byte)?;
+            offset += len;
+            match &bytes[offset..] {
+                // a `\r` directly followed by `\n` is terminated by the `\n`
+                [b'\r', b'\n', ..] => return Some((offset + 1, 1)),
+                [b'\n' | b'\r', ..] => return Some((offset, 1)),
+                // `\u{2028}` and `\u{2029}`
+                [0xE2, 0x80, 0xA8 | 0xA9, ..] if lead_byte == 0xE2 => return Some((offset, 3)),
+                _ => offset += 1,
+            }
+        },
     }
 }
 
+/// Decodes the chars of `bytes` using the given `encoding`, yielding the byte
+/// offset, byte length and value of each char.
+///
+/// Invalid UTF-8 sequences are decoded as U+FFFD.
+fn decode(
+    bytes: &[u8],
+    encoding: SourceEncoding,
+) -> impl Iterator<Item = (usize, usize, char)> + '_ {
+    let (utf8, latin1) = match encoding {
+        SourceEncoding::Utf8 => (Some(bytes), None),
+        SourceEncoding::Latin1 => (None, Some(bytes)),
+    };
+
+    let mut offset = 0;
+    let utf8 = utf8.into_iter().flat_map(|bytes| bytes.utf8_chunks());
+    let utf8 = utf8.flat_map(move |chunk| {
+        let start = offset;
+        let (valid, invalid) = (chunk.valid(), chunk.invalid());
+        offset += valid.len() + invalid.len();
+
+        let invalid = (!invalid.is_empty()).then_some((
+            start + valid.len(),
+            invalid.len(),
+            char::REPLACEMENT_CHARACTER,
+        ));
+        valid
+            .char_indices()
+            .map(move |(i, c)| (start + i, c.len_utf8(), c))
+            .chain(invalid)
+    });
+
+    let latin1 = latin1
+        .into_iter()
+        .flat_map(|bytes| bytes.iter().enumerate());
+    let latin1 = latin1.map(|(i, b)| (i, 1, char::from(*b)));
+
+    utf8.chain(latin1)
+}
+
 /// A line/column source position.
+///
+/// With the `serde` feature enabled, a position is serialized as
+/// `{"line": 0, "column": 6}`.
 #[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Eq, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SourcePosition {
     /// Line in the source file, 0-based.
     pub line: u32,
     /// Column in the source file, 0-based.
     ///
-    /// The column is given in UTF-16 code points.
+    /// The column is given in a [`ColumnUnit`], which is UTF-16 code units
+    /// unless specified otherwise.
     pub column: u32,
 }
 
@@ -178,6 +805,168 @@ impl SourcePosition {
     pub fn new(line: u32, column: u32) -> Self {
         Self { line, column }
     }
+
+    /// Create a new SourcePosition from the given 1-based line/column.
+    ///
+    /// Returns `None` if `line` or `column` is `0`. See [`FramePosition`].
+    pub fn from_one_based(line: u32, column: u32) -> Option<Self> {
+        FramePosition::new(line, column).to_source_position()
+    }
+
+    /// Converts this position to a 1-based [`FramePosition`].
+    ///
+    /// Returns `None` if the line or column is `u32::MAX`, as it can not be
+    /// represented as a 1-based position.
+    pub fn to_frame_position(self) -> Option<FramePosition> {
+        Some(FramePosition::new(
+            self.line.checked_add(1)?,
+            self.column.checked_add(1)?,
+        ))
+    }
+}
+
+/// A 1-based line/column position, as reported in JavaScript stack traces.
+///
+/// All the major engines (V8, SpiderMonkey and JavaScriptCore) report 1-based
+/// lines and columns in stack traces, with columns given in UTF-16 code units.
+/// This is in contrast to [`SourcePosition`] and source maps, which are 0-based.
+///
+/// # Examples
+///
+/// ```
+/// use js_source_scopes::{FramePosition, SourcePosition};
+///
+/// // at foo (http://example.com/bundle.js:1:13)
+/// let frame = FramePosition::new(1, 13);
+/// assert_eq!(frame.to_source_position(), Some(SourcePosition::new(0, 12)));
+/// assert_eq!(SourcePosition::new(0, 12).to_frame_position(), Some(frame));
+///
+/// // 0 is not a valid 1-based line
+/// assert_eq!(FramePosition::new(0, 13).to_source_position(), None);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Eq, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FramePosition {
+    /// Line in the source file, 1-based.
+    pub line: u32,
+    /// Column in the source file, 1-based.
+    ///
+    /// The column is given in UTF-16 code units.
+    pub column: u32,
+}
+
+impl FramePosition {
+    /// Create a new FramePosition with the given 1-based line/column.
+    pub fn new(line: u32, column: u32) -> Self {
+        Self { line, column }
+    }
+
+    /// Converts this position to a 0-based [`SourcePosition`].
+    ///
+    /// Returns `None` if the line or column is `0`.
+    pub fn to_source_position(self) -> Option<SourcePosition> {
+        Some(SourcePosition::new(
+            self.line.checked_sub(1)?,
+            self.column.checked_sub(1)?,
+        ))
+    }
+}
+
+/// The unit in which the column of a [`SourcePosition`] is given.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum ColumnUnit {
+    /// UTF-16 code units, as used by browsers and source maps.
+    #[default]
+    Utf16,
+    /// UTF-8 bytes, as used by byte-based tooling.
+    ///
+    /// For sources which are not valid UTF-8, this is the number of raw bytes
+    /// of the source.
+    Utf8,
+    /// Unicode scalar values, as used for example by Python.
+    UnicodeScalar,
+}
+
+impl ColumnUnit {
+    /// The length of the given character, which takes up `byte_len` bytes in
+    /// the source, in this unit.
+    fn len(self, c: char, byte_len: usize) -> usize {
+        match self {
+            ColumnUnit::Utf16 => c.len_utf16(),
+            ColumnUnit::Utf8 => byte_len,
+            ColumnUnit::UnicodeScalar => 1,
+        }
+    }
+}
+
+/// The characters that are treated as line terminators by a [`SourceContext`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum LineTerminators {
+    /// Only `\n` terminates a line, which includes `\r\n`.
+    #[default]
+    Newline,
+    /// All the line terminators defined by the ECMAScript specification, which
+    /// are `\n`, `\r\n`, a lone `\r`, `\u{2028}` and `\u{2029}`.
+    ///
+    /// This is how JavaScript engines count lines in stack traces.
+    Ecmascript,
+}
+
+/// The encoding of a source given as bytes to [`SourceContext::from_bytes_with_options`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum SourceEncoding {
+    /// UTF-8, with each invalid sequence treated as a single U+FFFD
+    /// replacement character.
+    #[default]
+    Utf8,
+    /// Latin-1 (ISO 8859-1), where each byte is a single character.
+    Latin1,
+}
+
+/// Options for creating a [`SourceContext`] using [`SourceContext::with_options`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SourceContextOptions {
+    /// The unit in which columns are given, unless specified otherwise.
+    pub column_unit: ColumnUnit,
+    /// The characters that terminate a line.
+    pub line_terminators: LineTerminators,
+    /// The encoding of the source.
+    ///
+    /// Sources given as `str` are always valid UTF-8, so this is only relevant
+    /// for [`SourceContext::from_bytes_with_options`], and ignored by
+    /// [`SourceContext::with_options`].
+    pub encoding: SourceEncoding,
+}
+
+/// A line of a [`SourceContext`] together with its surrounding lines, as
+/// returned by [`SourceContext::context_lines`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ContextLines<'a> {
+    /// The lines preceding the `context_line`.
+    pub pre_context: Vec<Cow<'a, str>>,
+    /// The line itself.
+    pub context_line: Cow<'a, str>,
+    /// The lines following the `context_line`.
+    pub post_context: Vec<Cow<'a, str>>,
 }
 
 /// An Error that can happen when building a [`SourceContext`].
@@ -202,6 +991,11 @@ mod tests {
         assert_eq!(ctx.offset_to_position(0), None);
         assert_eq!(ctx.position_to_offset(SourcePosition::new(0, 0)), None);
 
+        // offsets inside the last char of the source are out of bounds
+        let ctx = SourceContext::new("aö").unwrap();
+        assert_eq!(ctx.offset_to_position(1), Some(SourcePosition::new(0, 1)));
+        assert_eq!(ctx.offset_to_position(2), None);
+
         let src = "\n \r\naö¿¡\nőá…–🤮🚀¿ 한글 테스트\nz̴̢̈͜ä̴̺̟́ͅl̸̛̦͎̺͂̃̚͝g̷̦̲͊͋̄̌͝o̸͇̞̪͙̞͌̇̀̓̏͜\r\noh hai";
         let ctx = SourceContext::new(src).unwrap();
 
@@ -227,4 +1021,343 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn lines() {
+        let ctx = SourceContext::new("").unwrap();
+        assert_eq!(ctx.line_count(), 1);
+        assert_eq!(ctx.line(0).as_deref(), Some(""));
+        assert_eq!(ctx.line(1), None);
+
+        // many non-ASCII lines, each with its own checkpoint
+        let lines: Vec<_> = (0..100).map(|i| format!("line {i} ö\u{1F680}")).collect();
+        let src = lines.join("\r\n") + "\n";
+        let ctx = SourceContext::new(src.as_str()).unwrap();
+
+        assert_eq!(ctx.line_count(), 101);
+        for (i, expected) in lines.iter().enumerate() {
+            assert_eq!(ctx.line(i as u32).as_deref(), Some(expected.as_str()));
+            let range = ctx.line_range(i as u32).unwrap();
+            assert_eq!(
+                ctx.offset_to_position(range.start),
+                Some(SourcePosition::new(i as u32, 0))
+            );
+        }
+        assert_eq!(ctx.line(100).as_deref(), Some(""));
+        assert_eq!(ctx.line(101), None);
+
+        let context = ctx.context_lines(SourcePosition::new(99, 3), 3).unwrap();
+        assert_eq!(context.pre_context, &lines[96..99]);
+        assert_eq!(context.context_line, lines[99]);
+        assert_eq!(context.post_context, [""]);
+
+        let context = ctx.context_lines(SourcePosition::new(0, 0), 0).unwrap();
+        assert!(context.pre_context.is_empty());
+        assert!(context.post_context.is_empty());
+        assert!(ctx.context_lines(SourcePosition::new(101, 0), 5).is_none());
+    }
+
+    #[test]
+    fn column_units() {
+        let src = "\n \r\naö¿¡\nőá…–🤮🚀¿ 한글 테스트\nz̴̢̈͜ä̴̺̟́ͅl̸̛̦͎̺͂̃̚͝g̷̦̲͊͋̄̌͝o̸͇̞̪͙̞͌̇̀̓̏͜\r\noh hai";
+        let units = [
+            ColumnUnit::Utf16,
+            ColumnUnit::Utf8,
+            ColumnUnit::UnicodeScalar,
+        ];
+
+        for ctx_unit in units {
+            let options = SourceContextOptions {
+                column_unit: ctx_unit,
+                ..Default::default()
+            };
+            let ctx = SourceContext::with_options(src, options).unwrap();
+            assert_eq!(ctx.column_unit(), ctx_unit);
+
+            for (offset, _c) in src.char_indices() {
+                let line_start = src[..offset].rfind('\n').map_or(0, |idx| idx + 1);
+                let line_prefix = &src[line_start..offset];
+                let expected_columns = [
+                    line_prefix.encode_utf16().count(),
+                    line_prefix.len(),
+                    line_prefix.chars().count(),
+                ];
+
+                for (unit, expected) in units.into_iter().zip(expected_columns) {
+                    let position = ctx.offset_to_position_in(offset as u32, unit).unwrap();
+                    assert_eq!(position.column, expected as u32);
+                    assert_eq!(
+                        ctx.position_to_offset_in(position, unit),
+                        Some(offset as u32)
+                    );
+                }
+            }
+
+            // out of bounds
+            assert_eq!(
+                ctx.position_to_offset_in(SourcePosition::new(1, 3), ColumnUnit::Utf8),
+                None
+            );
+            assert_eq!(
+                ctx.offset_to_position_in(150, ColumnUnit::UnicodeScalar),
+                None
+            );
+        }
+
+        let ctx = SourceContext::new(src).unwrap();
+        // `🤮` starts at UTF-16 column 4 of line 3
+        assert_eq!(
+            ctx.convert_column(3, 4, ColumnUnit::Utf16, ColumnUnit::Utf8),
+            Some(10)
+        );
+        assert_eq!(
+            ctx.convert_column(3, 10, ColumnUnit::Utf8, ColumnUnit::UnicodeScalar),
+            Some(4)
+        );
+        assert_eq!(
+            ctx.convert_column(3, 5, ColumnUnit::UnicodeScalar, ColumnUnit::Utf16),
+            Some(6)
+        );
+    }
+
+    #[test]
+    fn line_terminators() {
+        let src = "a\nb\r\nc\rd\u{2028}e\u{2029}f";
+
+        let ctx = SourceContext::new(src).unwrap();
+        assert_eq!(ctx.line_count(), 3);
+        assert_eq!(ctx.line(2).as_deref(), Some("c\rd\u{2028}e\u{2029}f"));
+
+        let options = SourceContextOptions {
+            line_terminators: LineTerminators::Ecmascript,
+            ..Default::default()
+        };
+        let ctx = SourceContext::with_options(src, options).unwrap();
+        assert_eq!(ctx.line_count(), 6);
+
+        let lines: Vec<_> = (0..6).map(|line| ctx.line(line).unwrap()).collect();
+        assert_eq!(lines, ["a", "b", "c", "d", "e", "f"]);
+
+        for (line, expected) in lines.iter().enumerate() {
+            let offset = ctx.position_to_offset(SourcePosition::new(line as u32, 0));
+            let offset = offset.unwrap() as usize;
+            assert_eq!(&src[offset..offset + 1], *expected);
+            assert_eq!(
+                ctx.offset_to_position(offset as u32),
+                Some(SourcePosition::new(line as u32, 0))
+            );
+        }
+        // out of bounds columns do not spill into the next line
+        assert_eq!(ctx.position_to_offset(SourcePosition::new(3, 2)), None);
+
+        // offsets inside a terminator ending the source are out of bounds
+        for src in ["a\u{2028}", "a\u{2029}"] {
+            let ctx = SourceContext::with_options(src, options).unwrap();
+            assert_eq!(ctx.offset_to_position(1), Some(SourcePosition::new(0, 1)));
+            assert_eq!(ctx.offset_to_position(2), None);
+            assert_eq!(ctx.offset_to_position(3), None);
+            assert_eq!(
+                ctx.offsets_to_positions(&[3, 0, 2, 1]),
+                [
+                    None,
+                    Some(SourcePosition::new(0, 0)),
+                    None,
+                    Some(SourcePosition::new(0, 1))
+                ]
+            );
+        }
+
+        // many lines spanning multiple checkpoints each
+        let line = "xö".repeat(100);
+        let src = format!("{line}\u{2028}").repeat(30);
+        let ctx = SourceContext::with_options(src.as_str(), options).unwrap();
+        assert_eq!(ctx.line_count(), 31);
+        // each line takes 300 bytes, and the terminator another 3
+        let position = SourcePosition::new(25, 150);
+        let offset = 25 * 303 + 225;
+        assert_eq!(ctx.position_to_offset(position), Some(offset));
+        assert_eq!(ctx.offset_to_position(offset), Some(position));
+        assert_eq!(ctx.line(29).as_deref(), Some(line.as_str()));
+        assert_eq!(ctx.line(30).as_deref(), Some(""));
+    }
+
+    #[test]
+    fn long_lines() {
+        let line = "aö🚀".repeat(1000);
+        let src = format!("ascii\n{line}\r\n{line}");
+        let ctx = SourceContext::new(src.as_str()).unwrap();
+        assert_eq!(ctx.line_count(), 3);
+        assert_eq!(ctx.line(1).as_deref(), Some(line.as_str()));
+        assert_eq!(ctx.line(2).as_deref(), Some(line.as_str()));
+
+        for (offset, c) in src.char_indices().skip(6) {
+            let line_start = src[..offset].rfind('\n').unwrap() + 1;
+            let prefix = &src[line_start..offset];
+            let line = if line_start == 6 { 1 } else { 2 };
+
+            let expected = SourcePosition::new(line, prefix.encode_utf16().count() as u32);
+            assert_eq!(ctx.offset_to_position(offset as u32), Some(expected));
+            assert_eq!(ctx.position_to_offset(expected), Some(offset as u32));
+
+            let scalars = prefix.chars().count() as u32;
+            let position = ctx.offset_to_position_in(offset as u32, ColumnUnit::UnicodeScalar);
+            assert_eq!(position, Some(SourcePosition::new(line, scalars)));
+
+            // offsets and columns pointing into the middle of a char round up,
+            // unless the char is the last one of the source
+            if c.len_utf8() > 1 {
+                let next = (offset + c.len_utf8()) as u32;
+                let position = ctx.offset_to_position_in(offset as u32 + 1, ColumnUnit::Utf8);
+                let expected = (next as usize) < src.len();
+                let expected = expected.then_some(next - line_start as u32);
+                assert_eq!(position.map(|p| p.column), expected);
+            }
+        }
+
+        // out of bounds
+        let line_len = line.encode_utf16().count() as u32;
+        assert!(ctx
+            .position_to_offset(SourcePosition::new(1, line_len + 1))
+            .is_some());
+        assert_eq!(
+            ctx.position_to_offset(SourcePosition::new(1, line_len + 2)),
+            None
+        );
+        assert_eq!(
+            ctx.position_to_offset(SourcePosition::new(2, line_len)),
+            None
+        );
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        // a truncated `ö`, a lone continuation byte and an invalid `\u{2028}`
+        let src = b"a\xC3\n\x80b\xE2\x80\xA8c\xE2\x80d\n\xF0\x9F\x9A\x80e".repeat(100);
+        let options = SourceContextOptions {
+            line_terminators: LineTerminators::Ecmascript,
+            ..Default::default()
+        };
+        let ctx = SourceContext::from_bytes_with_options(&src[..], options).unwrap();
+        assert_eq!(ctx.line_count(), 301);
+        assert_eq!(ctx.line(0).as_deref(), Some("a\u{FFFD}"));
+        assert_eq!(ctx.line(1).as_deref(), Some("\u{FFFD}b"));
+        assert_eq!(ctx.line(2).as_deref(), Some("c\u{FFFD}d"));
+        assert_eq!(ctx.line(3).as_deref(), Some("\u{1F680}ea\u{FFFD}"));
+
+        assert_eq!(
+            ctx.offset_to_position_in(7, ColumnUnit::UnicodeScalar),
+            Some(SourcePosition::new(2, 0))
+        );
+        // the truncated sequence `\xE2\x80` is a single char
+        assert_eq!(ctx.offset_to_position(9), Some(SourcePosition::new(2, 1)));
+        assert_eq!(ctx.offset_to_position(10), Some(SourcePosition::new(2, 2)));
+        assert_eq!(ctx.offset_to_position(11), Some(SourcePosition::new(2, 2)));
+        assert_eq!(
+            ctx.offset_to_position_in(11, ColumnUnit::Utf8),
+            Some(SourcePosition::new(2, 3))
+        );
+
+        // roundtrip all the char boundaries, as decoded lossily
+        let mut offset = 0;
+        for chunk in src.utf8_chunks() {
+            let valid = chunk.valid().char_indices().map(|(i, _)| offset + i);
+            let invalid = (!chunk.invalid().is_empty()).then_some(offset + chunk.valid().len());
+            for char_offset in valid.chain(invalid) {
+                let position = ctx.offset_to_position(char_offset as u32).unwrap();
+                assert_eq!(ctx.position_to_offset(position), Some(char_offset as u32));
+            }
+            offset += chunk.valid().len() + chunk.invalid().len();
+        }
+    }
+
+    #[test]
+    fn latin1() {
+        let src = b"caf\xE9\r\xE2\x80\xA8\nx".to_vec();
+        let options = SourceContextOptions {
+            line_terminators: LineTerminators::Ecmascript,
+            encoding: SourceEncoding::Latin1,
+            ..Default::default()
+        };
+        let ctx = SourceContext::from_bytes_with_options(src, options).unwrap();
+
+        // `\u{2028}` does not exist in Latin-1
+        assert_eq!(ctx.line_count(), 3);
+        assert_eq!(ctx.line(0).as_deref(), Some("café"));
+        assert_eq!(ctx.line(1).as_deref(), Some("\u{E2}\u{80}\u{A8}"));
+        assert!(matches!(ctx.line(2), Some(Cow::Borrowed("x"))));
+
+        assert_eq!(ctx.offset_to_position(3), Some(SourcePosition::new(0, 3)));
+        assert_eq!(ctx.offset_to_position(7), Some(SourcePosition::new(1, 2)));
+        assert_eq!(ctx.position_to_offset(SourcePosition::new(2, 0)), Some(9));
+        assert_eq!(ctx.into_inner().len(), 10);
+
+        // sources given as `str` are always decoded as UTF-8
+        let ctx = SourceContext::with_options("é=1", options).unwrap();
+        assert_eq!(ctx.offset_to_position(2), Some(SourcePosition::new(0, 1)));
+        assert_eq!(ctx.line(0).as_deref(), Some("é=1"));
+    }
+
+    #[test]
+    fn batch_conversions() {
+        let line = "aö🚀 ".repeat(200);
+        let src = format!("{line}\n\n{line}\nascii");
+        let ctx = SourceContext::new(src.as_str()).unwrap();
+
+        // in reverse, with duplicates, and out of bounds
+        let mut offsets: Vec<u32> = (0..src.len() as u32 + 2).rev().collect();
+        offsets.extend([5, 5, 0]);
+
+        for unit in [
+            ColumnUnit::Utf16,
+            ColumnUnit::Utf8,
+            ColumnUnit::UnicodeScalar,
+        ] {
+            let positions = ctx.offsets_to_positions_in(&offsets, unit);
+            for (offset, position) in offsets.iter().zip(&positions) {
+                assert_eq!(*position, ctx.offset_to_position_in(*offset, unit));
+            }
+
+            let mut positions: Vec<_> = positions.into_iter().flatten().collect();
+            positions.extend([
+                SourcePosition::new(0, 10_000),
+                SourcePosition::new(1, 1),
+                SourcePosition::new(0, 3),
+                SourcePosition::new(0, 4),
+                SourcePosition::new(5, 0),
+            ]);
+            let offsets = ctx.positions_to_offsets_in(&positions, unit);
+            for (position, offset) in positions.iter().zip(offsets) {
+                assert_eq!(offset, ctx.position_to_offset_in(*position, unit));
+            }
+        }
+    }
+
+    #[test]
+    fn frame_positions() {
+        let options = SourceContextOptions {
+            column_unit: ColumnUnit::Utf8,
+            ..Default::default()
+        };
+        let ctx = SourceContext::with_options("\nlet ö = () => {};", options).unwrap();
+
+        // frame positions always use 1-based UTF-16 columns
+        let frame = FramePosition::new(2, 10);
+        assert_eq!(ctx.frame_position_to_offset(frame), Some(11));
+        assert_eq!(ctx.offset_to_frame_position(11), Some(frame));
+        assert_eq!(ctx.offset_to_position(11), Some(SourcePosition::new(1, 10)));
+
+        assert_eq!(ctx.frame_position_to_offset(FramePosition::new(0, 1)), None);
+        assert_eq!(ctx.frame_position_to_offset(FramePosition::new(1, 0)), None);
+        assert_eq!(
+            SourcePosition::from_one_based(1, 1),
+            Some(SourcePosition::new(0, 0))
+        );
+        assert_eq!(
+            SourcePosition::new(0, 0).to_frame_position(),
+            Some(FramePosition::new(1, 1))
+        );
+        // the maximum 0-based line or column has no 1-based equivalent
+        assert_eq!(SourcePosition::new(u32::MAX, 0).to_frame_position(), None);
+        assert_eq!(SourcePosition::new(0, u32::MAX).to_frame_position(), None);
+    }
 }
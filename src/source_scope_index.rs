@@ -0,0 +1,168 @@
+use std::ops::Range;
+
+use crate::{
+    FramePosition, IndexedScope, ScopeIndex, ScopeIndexError, ScopeIndexErrorKind,
+    ScopeLookupResult, SourceContext, SourcePosition,
+};
+
+/// A [`ScopeIndex`] combined with the [`SourceContext`] of the source it was
+/// created from, allowing lookups by line/column.
+///
+/// # Examples
+///
+/// ```
+/// use js_source_scopes::{
+///     ScopeIndex, ScopeLookupResult, SourceContext, SourcePosition, SourceScopeIndex,
+/// };
+///
+/// let src = "function outer() {\n  return () => {};\n}";
+/// let scopes = vec![(0..39, Some(String::from("outer"))), (28..36, None)];
+///
+/// let ctx = SourceContext::new(src).unwrap();
+/// let index = ScopeIndex::new(scopes).unwrap();
+/// let index = SourceScopeIndex::new(ctx, index);
+///
+/// assert_eq!(
+///     index.lookup_position(SourcePosition::new(1, 2)),
+///     Some(ScopeLookupResult::NamedScope("outer"))
+/// );
+/// // browsers report 1-based lines and columns
+/// assert_eq!(
+///     index.lookup_one_based(2, 12),
+///     Some(ScopeLookupResult::AnonymousScope)
+/// );
+/// ```
+pub struct SourceScopeIndex<T> {
+    ctx: SourceContext<T>,
+    index: ScopeIndex,
+}
+
+impl<T> SourceScopeIndex<T> {
+    /// Combines the given [`SourceContext`] and [`ScopeIndex`].
+    ///
+    /// The `index` is expected to be created from the same source as `ctx`.
+    pub fn new(ctx: SourceContext<T>, index: ScopeIndex) -> Self {
+        Self { ctx, index }
+    }
+
+    /// Merges multiple indexes into one for the concatenated source in `ctx`.
+    ///
+    /// Each index is rebased to start at the beginning of the given 0-based
+    /// line of the concatenated source. Fails if any of the lines is outside
+    /// of the source.
+    ///
+    /// See [`ScopeIndex::merge`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::{ScopeIndex, ScopeLookupResult, SourceContext, SourceScopeIndex};
+    ///
+    /// let a = "function a() {}";
+    /// let b = "function b() {}";
+    /// let concatenated = format!("{a}\n// separator\n{b}");
+    ///
+    /// let a_idx = ScopeIndex::new(vec![(0..15, Some(String::from("a")))]).unwrap();
+    /// let b_idx = ScopeIndex::new(vec![(0..15, Some(String::from("b")))]).unwrap();
+    ///
+    /// let ctx = SourceContext::new(concatenated.as_str()).unwrap();
+    /// let index = SourceScopeIndex::merge(ctx, [(0, &a_idx), (2, &b_idx)]).unwrap();
+    /// assert_eq!(
+    ///     index.lookup_one_based(3, 10),
+    ///     Some(ScopeLookupResult::NamedScope("b"))
+    /// );
+    /// ```
+    pub fn merge<'a>(
+        ctx: SourceContext<T>,
+        indexes: impl IntoIterator<Item = (u32, &'a ScopeIndex)>,
+    ) -> Result<Self, ScopeIndexError> {
+        let indexes = indexes
+            .into_iter()
+            .map(|(line, index)| {
+                let offset = ctx
+                    .position_to_offset(SourcePosition::new(line, 0))
+                    .ok_or(ScopeIndexErrorKind::InvalidOffset)?;
+                Ok((offset, index))
+            })
+            .collect::<Result<Vec<_>, ScopeIndexError>>()?;
+        let index = ScopeIndex::merge(indexes)?;
+        Ok(Self { ctx, index })
+    }
+
+    /// The [`SourceContext`] used to convert positions.
+    pub fn source_context(&self) -> &SourceContext<T> {
+        &self.ctx
+    }
+
+    /// The underlying [`ScopeIndex`].
+    pub fn scope_index(&self) -> &ScopeIndex {
+        &self.index
+    }
+
+    /// Splits this into the [`SourceContext`] and [`ScopeIndex`].
+    pub fn into_parts(self) -> (SourceContext<T>, ScopeIndex) {
+        (self.ctx, self.index)
+    }
+
+    /// Looks up the scope corresponding to the given 0-based `position`.
+    ///
+    /// Returns `None` if the `position` is outside of the source.
+    pub fn lookup_position(&self, position: SourcePosition) -> Option<ScopeLookupResult<'_>> {
+        let offset = self.ctx.position_to_offset(position)?;
+        Some(self.index.lookup(offset))
+    }
+
+    /// Looks up the scope corresponding to the given 1-based `position`, as
+    /// reported in browser stack traces.
+    ///
+    /// Returns `None` if the `position` is outside of the source, or invalid.
+    pub fn lookup_frame_position(&self, position: FramePosition) -> Option<ScopeLookupResult<'_>> {
+        let offset = self.ctx.frame_position_to_offset(position)?;
+        Some(self.index.lookup(offset))
+    }
+
+    /// Looks up the scope corresponding to the given 1-based `line` and `column`,
+    /// as they are reported in browser stack traces.
+    ///
+    /// Returns `None` if the position is outside of the source, or if `line`
+    /// or `column` is `0`. See [`SourceScopeIndex::lookup_frame_position`].
+    pub fn lookup_one_based(&self, line: u32, column: u32) -> Option<ScopeLookupResult<'_>> {
+        self.lookup_frame_position(FramePosition::new(line, column))
+    }
+
+    /// Returns all the scopes intersecting the given range of 0-based `lines`.
+    ///
+    /// See [`ScopeIndex::scopes_in_range`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use js_source_scopes::{ScopeIndex, SourceContext, SourceScopeIndex};
+    ///
+    /// let src = "function a() {}\nfunction b() {}\nfunction c() {}";
+    /// let scopes = vec![
+    ///     (0..15, Some(String::from("a"))),
+    ///     (16..31, Some(String::from("b"))),
+    ///     (32..47, Some(String::from("c"))),
+    /// ];
+    ///
+    /// let ctx = SourceContext::new(src).unwrap();
+    /// let index = SourceScopeIndex::new(ctx, ScopeIndex::new(scopes).unwrap());
+    ///
+    /// let visible: Vec<_> = index.scopes_in_lines(1..3).map(|s| s.name).collect();
+    /// assert_eq!(visible, [Some("b"), Some("c")]);
+    /// ```
+    pub fn scopes_in_lines(
+        &self,
+        lines: Range<u32>,
+    ) -> impl Iterator<Item = IndexedScope<'_>> + '_ {
+        // lines beyond the end of the source extend to the end of the source
+        let line_start = |line| {
+            self.ctx
+                .position_to_offset(SourcePosition::new(line, 0))
+                .unwrap_or(u32::MAX)
+        };
+        let range = line_start(lines.start)..line_start(lines.end);
+        self.index.scopes_in_range(range)
+    }
+}
@@ -1,4 +1,6 @@
-use js_source_scopes::{extract_scope_names, Scopes};
+use std::collections::HashSet;
+
+use js_source_scopes::{extract_scope_names, NameComponent, ScopeName, Scopes};
 
 fn scope_strs(scopes: Scopes) -> Vec<Option<String>> {
     scopes
@@ -274,3 +276,27 @@ fn extract_computed_properties() {
     ];
     assert_eq!(scopes, expected);
 }
+
+#[test]
+fn scope_names_are_comparable() {
+    let src = r#"
+        function fn_decl() {}
+        function fn_decl() {}
+        "#;
+    let scopes = extract_scope_names(src).unwrap();
+    let names: Vec<_> = scopes.into_iter().filter_map(|s| s.1).collect();
+
+    // same text, but different source ranges
+    assert_eq!(names[0].to_string(), names[1].to_string());
+    assert_ne!(names[0], names[1]);
+
+    let expected =
+        ScopeName::from_components([NameComponent::source_identifier("fn_decl", 18..25)]);
+    assert_eq!(names[0], expected);
+
+    let unique: HashSet<_> = names.iter().chain(names.iter()).cloned().collect();
+    assert_eq!(unique.len(), 2);
+
+    let sent = std::thread::spawn(move || names).join().unwrap();
+    assert_eq!(sent[0], expected);
+}
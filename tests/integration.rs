@@ -1,8 +1,8 @@
 use std::ops::Range;
 
 use js_source_scopes::{
-    extract_scope_names, NameResolver, ScopeIndex, ScopeLookupResult, ScopeName, SourceContext,
-    SourcePosition,
+    extract_scope_names, FramePosition, NameResolution, NameResolver, ScopeIndex, ScopeIndexView,
+    ScopeLookupResult, ScopeName, SourceContext, SourcePosition, SourceScopeIndex,
 };
 
 fn fixture(name: &str) -> String {
@@ -51,22 +51,16 @@ fn resolves_scope_names() {
     let scopes = extract_scope_names(&src).unwrap();
     // dbg!(&scopes);
 
-    let scopes: Vec<_> = scopes
-        .into_iter()
-        .map(|s| (s.0, s.1.map(|n| n.to_string()).filter(|s| !s.is_empty())))
-        .collect();
-    let index = ScopeIndex::new(scopes).unwrap();
+    let index = ScopeIndex::try_from(scopes).unwrap();
 
     let ctx = SourceContext::new(&src).unwrap();
+    let index = SourceScopeIndex::new(ctx, index);
 
     use ScopeLookupResult::*;
+    // NOTE: the browsers use 1-based line/column numbers
     let lookup = |l: u32, c: u32| {
-        // NOTE: the browsers use 1-based line/column numbers, while the crates uses
-        // 0-based numbers everywhere
-        let offset = ctx
-            .position_to_offset(SourcePosition::new(l - 1, c - 1))
-            .unwrap();
-        index.lookup(offset)
+        let position = FramePosition::new(l, c);
+        index.lookup_frame_position(position).unwrap()
     };
 
     // objectLiteralAnon@http://127.0.0.1:8080/sync.mjs:84:11
@@ -254,6 +248,102 @@ fn should_resolve_name_from_function_keyword_token() {
     assert_eq!(resolved_scopes[0].2, Some("initServer".into()));
 }
 
+#[test]
+fn resolves_names_with_details() {
+    let resolve = |fixture_name: &str, map_name: &str, minified_name: &str| {
+        let minified = fixture(fixture_name);
+        let map = fixture(map_name);
+        let scopes = extract_scope_names(&minified).unwrap();
+        let name = scopes
+            .into_iter()
+            .filter_map(|(_, name)| name)
+            .find(|name| name.to_string() == minified_name)
+            .unwrap();
+
+        let ctx = SourceContext::new(&minified).unwrap();
+        let sm = sourcemap::decode_slice(map.as_bytes()).unwrap();
+        let resolver = NameResolver::new(&ctx, &sm);
+        let resolved = resolver.resolve_name_detailed(&name);
+        assert_eq!(resolved.to_string(), resolver.resolve_name(&name));
+
+        let components: Vec<_> = resolved
+            .components
+            .iter()
+            .map(|c| {
+                let original = c
+                    .original
+                    .map(|o| (o.source.unwrap().to_owned(), o.position));
+                (c.text.to_owned(), c.resolution, original)
+            })
+            .collect();
+        (resolved.is_fully_resolved(), components)
+    };
+
+    let (fully_resolved, components) = resolve("simple/minified.js", "simple/minified.js.map", "t");
+    assert!(fully_resolved);
+    assert_eq!(
+        components,
+        [(
+            "abcd".into(),
+            NameResolution::SourceMapName,
+            Some((
+                "tests/fixtures/simple/original.js".into(),
+                SourcePosition::new(1, 9)
+            ))
+        )]
+    );
+
+    let (fully_resolved, components) = resolve(
+        "ts-function-name/minified.js",
+        "ts-function-name/minified.js.map",
+        "ab",
+    );
+    assert!(fully_resolved);
+    assert_eq!(components[0].0, "initServer");
+    assert_eq!(components[0].1, NameResolution::PrecedingToken);
+
+    let (fully_resolved, components) = resolve(
+        "ts-function-name/sentry-repro.js",
+        "ts-function-name/sentry-repro.js.map",
+        "a",
+    );
+    assert!(!fully_resolved);
+    assert_eq!(components[0].0, "a");
+    assert_eq!(components[0].1, NameResolution::Unresolved);
+    assert!(components[0].2.is_some());
+}
+
+#[test]
+fn resolves_scope_locations() {
+    let minified = fixture("simple/minified.js");
+    let map = fixture("simple/minified.js.map");
+    let scopes = extract_scope_names(&minified).unwrap();
+
+    let ctx = SourceContext::new(&minified).unwrap();
+    let sm = sourcemap::decode_slice(map.as_bytes()).unwrap();
+    let resolver = NameResolver::new(&ctx, &sm);
+
+    let (range, name) = scopes.into_iter().find(|(_, name)| name.is_some()).unwrap();
+    let scope = resolver.resolve_scope(range.clone(), name.as_ref());
+
+    assert_eq!(scope.range, range);
+    assert_eq!(scope.name.unwrap().to_string(), "abcd");
+
+    let name_location = scope.name_location.unwrap();
+    assert_eq!(
+        name_location.source,
+        Some("tests/fixtures/simple/original.js")
+    );
+    assert_eq!(name_location.position, SourcePosition::new(1, 9));
+
+    let scope_location = scope.scope_location.unwrap();
+    assert_eq!(
+        scope_location.source,
+        Some("tests/fixtures/simple/original.js")
+    );
+    assert_eq!(scope_location.position, SourcePosition::new(1, 0));
+}
+
 #[test]
 fn should_resolve_name_from_function_keyword_token_three_segments() {
     // Variant where the source map has THREE segments around the function:
@@ -280,3 +370,134 @@ fn should_resolve_name_from_function_keyword_token_three_segments() {
     // If the fix is improved to walk further back, update this to "initServer".
     assert_eq!(func_scope.2, Some("a".into()));
 }
+
+#[test]
+fn name_resolver_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<NameResolver<'static, &str>>();
+}
+
+#[test]
+fn resolves_scope_names_from_original_sources() {
+    // Same as above, but falling back to parsing the `sourcesContent`.
+    let minified = fixture("ts-function-name/sentry-repro.js");
+    let map = fixture("ts-function-name/sentry-repro.js.map");
+
+    let scopes = extract_scope_names(&minified).unwrap();
+    let (range, name) = scopes
+        .into_iter()
+        .find(|(_, name)| name.as_ref().is_some_and(|n| n.to_string() == "a"))
+        .unwrap();
+
+    let ctx = SourceContext::new(&minified).unwrap();
+    let sm = sourcemap::decode_slice(map.as_bytes()).unwrap();
+
+    let resolver = NameResolver::new(&ctx, &sm);
+    let scope = resolver.resolve_scope(range.clone(), name.as_ref());
+    assert_eq!(scope.name.unwrap().to_string(), "a");
+
+    let resolver = NameResolver::new(&ctx, &sm).with_original_sources();
+    let scope = resolver.resolve_scope(range.clone(), name.as_ref());
+
+    let name = scope.name.unwrap();
+    assert!(name.is_fully_resolved());
+    assert_eq!(name.to_string(), "initServer");
+    assert_eq!(
+        name.components[0].resolution,
+        NameResolution::OriginalSource
+    );
+
+    let name_location = scope.name_location.unwrap();
+    assert_eq!(name_location.source, Some("original.js"));
+    assert_eq!(name_location.position, SourcePosition::new(0, 9));
+
+    let scopes = extract_scope_names(&minified).unwrap();
+    let index = ScopeIndex::from_resolved_scopes(scopes, &resolver).unwrap();
+    assert_eq!(
+        index.lookup(range.start + 1),
+        ScopeLookupResult::NamedScope("initServer")
+    );
+}
+
+#[test]
+fn scope_index_binary_roundtrip() {
+    let src = fixture("trace/sync.mjs");
+
+    let scopes = extract_scope_names(&src).unwrap();
+    let index = ScopeIndex::from_scopes(scopes).unwrap();
+
+    let buf = index.to_bytes().unwrap();
+    let loaded = ScopeIndex::from_bytes(&buf).unwrap();
+
+    let view = ScopeIndexView::parse(&buf).unwrap();
+
+    assert!(index.iter().eq(loaded.iter()));
+    assert!(index.iter().eq(view.iter()));
+    for offset in 0..src.len() as u32 {
+        assert_eq!(index.lookup(offset), loaded.lookup(offset));
+        assert_eq!(index.lookup(offset), view.lookup(offset));
+        assert_eq!(index.lookup_scope(offset), view.lookup_scope(offset));
+        assert!(index.lookup_stack(offset).eq(view.lookup_stack(offset)));
+    }
+
+    for (_, name) in index.iter() {
+        if let ScopeLookupResult::NamedScope(name) = name {
+            let scopes: Vec<_> = index.scopes_by_name(name).collect();
+            assert!(!scopes.is_empty());
+            assert!(scopes.iter().all(|s| s.name == Some(name)));
+            assert!(scopes.into_iter().eq(view.scopes_by_name(name)));
+            assert!(index
+                .scopes_by_name_prefix(name)
+                .eq(view.scopes_by_name_prefix(name)));
+        }
+    }
+    assert!(index
+        .scopes_by_name_prefix("")
+        .eq(view.scopes_by_name_prefix("")));
+}
+
+#[test]
+fn scope_index_merge() {
+    let sync = fixture("trace/sync.mjs");
+    let shared = fixture("trace/shared.mjs");
+    let concatenated = format!("{sync}\n{shared}");
+
+    let sync_index = ScopeIndex::from_scopes(extract_scope_names(&sync).unwrap()).unwrap();
+    let shared_index = ScopeIndex::from_scopes(extract_scope_names(&shared).unwrap()).unwrap();
+    let expected = ScopeIndex::from_scopes(extract_scope_names(&concatenated).unwrap()).unwrap();
+
+    let offset = sync.len() as u32 + 1;
+    let merged = ScopeIndex::merge([(0, &sync_index), (offset, &shared_index)]).unwrap();
+    assert!(merged.iter().eq(expected.iter()));
+
+    let ctx = SourceContext::new(concatenated.as_str()).unwrap();
+    // `sync` ends with a newline, followed by the separating empty line
+    let line = sync.lines().count() as u32 + 1;
+    let merged = SourceScopeIndex::merge(ctx, [(0, &sync_index), (line, &shared_index)]).unwrap();
+    assert!(merged.scope_index().iter().eq(expected.iter()));
+}
+
+#[test]
+fn scope_index_from_resolved_scopes() {
+    let minified = fixture("off-by-one/test.min.js");
+    let map = fixture("off-by-one/test.map");
+
+    let scopes = extract_scope_names(&minified).unwrap();
+    let expected = resolve_original_scopes(&minified, &map, scopes.clone());
+
+    let ctx = SourceContext::new(&minified).unwrap();
+    let sm = sourcemap::decode_slice(map.as_bytes()).unwrap();
+    let resolver = NameResolver::new(&ctx, &sm);
+    let index = ScopeIndex::from_resolved_scopes(scopes, &resolver).unwrap();
+
+    for (range, minified, original) in expected {
+        let scope = index.lookup_scope(range.start).unwrap();
+        assert_eq!(scope.range, range);
+        assert_eq!(scope.name, original.as_deref());
+        assert_eq!(scope.minified_name, minified.as_deref());
+    }
+
+    let buf = index.to_bytes().unwrap();
+    let view = ScopeIndexView::parse(&buf).unwrap();
+    assert_eq!(view.lookup_scope(0), index.lookup_scope(0));
+}
@@ -0,0 +1,82 @@
+#![cfg(feature = "serde")]
+
+use js_source_scopes::{
+    extract_scope_names, OwnedScopeLookupResult, ScopeIndex, ScopeLookupResult, Scopes,
+    SourcePosition,
+};
+use serde_json::json;
+
+#[test]
+fn serializes_scopes() {
+    let src = "const arrowFnExpr = (a) => a; (function () {})()";
+    let mut scopes = extract_scope_names(src).unwrap();
+    scopes.sort_by_key(|s| s.0.start);
+
+    let serialized = serde_json::to_value(&scopes).unwrap();
+    let expected = json!([
+        [
+            {"start": 20, "end": 28},
+            [{"text": "arrowFnExpr", "range": {"start": 6, "end": 17}}]
+        ],
+        [{"start": 31, "end": 45}, null],
+    ]);
+    assert_eq!(serialized, expected);
+
+    let roundtrip: Scopes = serde_json::from_value(serialized).unwrap();
+    assert_eq!(roundtrip, scopes);
+}
+
+#[test]
+fn serializes_synthetic_components() {
+    let src = "class Foo { get bar() {} }";
+    let scopes = extract_scope_names(src).unwrap();
+    let name = scopes[1].1.clone().unwrap();
+
+    let serialized = serde_json::to_value(&name).unwrap();
+    let expected = json!([
+        {"text": "get "},
+        {"text": "Foo", "range": {"start": 6, "end": 9}},
+        {"text": "."},
+        {"text": "bar", "range": {"start": 16, "end": 19}},
+    ]);
+    assert_eq!(serialized, expected);
+
+    let roundtrip = serde_json::from_value(serialized).unwrap();
+    assert_eq!(name, roundtrip);
+}
+
+#[test]
+fn serializes_positions() {
+    let position = SourcePosition::new(1, 9);
+
+    let serialized = serde_json::to_value(position).unwrap();
+    assert_eq!(serialized, json!({"line": 1, "column": 9}));
+
+    let roundtrip: SourcePosition = serde_json::from_value(serialized).unwrap();
+    assert_eq!(roundtrip, position);
+}
+
+#[test]
+fn serializes_lookup_results() {
+    let scopes = vec![(5..25, Some(String::from("parent"))), (30..50, None)];
+    let idx = ScopeIndex::new(scopes).unwrap();
+
+    let cases = [
+        (7, json!({"kind": "named_scope", "name": "parent"})),
+        (40, json!({"kind": "anonymous_scope"})),
+        (60, json!({"kind": "unknown"})),
+    ];
+    for (offset, expected) in cases {
+        let result = idx.lookup(offset);
+        assert_eq!(serde_json::to_value(result).unwrap(), expected);
+
+        let owned: OwnedScopeLookupResult = serde_json::from_value(expected).unwrap();
+        assert_eq!(owned, result.into_owned());
+        assert_eq!(owned.as_lookup_result(), result);
+    }
+
+    assert_eq!(
+        OwnedScopeLookupResult::from(ScopeLookupResult::NamedScope("parent")),
+        OwnedScopeLookupResult::NamedScope("parent".into())
+    );
+}